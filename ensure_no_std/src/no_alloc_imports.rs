@@ -7,7 +7,7 @@ pub fn test_archive_read() {
     let offset = 0;
     let (rest, header) = NewcHeader::from_bytes((ARCHIVE_DATA, offset)).unwrap();
 
-    let name = header.name();
+    let name = header.name().unwrap();
     assert!(name.len() > 0, "Header should have a non-empty name");
 
     let _mode = header.mode();
@@ -17,7 +17,7 @@ pub fn test_archive_read() {
     assert!(rest.1 > offset, "Parsing should advance offset");
 
     let common = header.as_header();
-    assert!(common.name == name, "Name should match in common header");
+    assert!(common.name == header.name_bytes(), "Name should match in common header");
 }
 
 pub fn test_header_fields() {
@@ -36,7 +36,7 @@ pub fn test_header_fields() {
     let _rdevminor = header.rdevminor();
     let _namesize = header.namesize();
     let _check = header.check();
-    let name = header.name();
+    let name = header.name().unwrap();
 
     assert!(name.len() > 0);
 
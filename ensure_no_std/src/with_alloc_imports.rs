@@ -1,7 +1,6 @@
 extern crate alloc;
 
 use alloc::boxed::Box;
-use alloc::string::ToString;
 use alloc::vec::Vec;
 use deku::prelude::*;
 use no_std_io2::io::Cursor;
@@ -16,7 +15,7 @@ pub fn test_archive_reader() {
     assert!(archive.objects.inner.len() > 0, "Archive should contain objects");
 
     let first_object = &archive.objects.inner[0];
-    let name = first_object.header.name();
+    let name = first_object.header.name().unwrap();
     assert!(name.len() > 0, "First object should have a name");
 }
 
@@ -34,21 +33,21 @@ pub fn test_newc_header_creation() {
         rdev: None,
         rdevmajor: Some(0),
         rdevminor: Some(0),
-        name: "test.txt".to_string(),
+        name: "test.txt".into(),
     };
 
-    let newc = NewcHeader::from_header(header, 0);
+    let newc = NewcHeader::from_header(header, 0).unwrap();
 
     assert!(newc.ino() == 42);
     assert!(newc.mode() == 0o100644);
-    assert!(newc.name() == "test.txt");
+    assert!(newc.name() == Some("test.txt"));
     assert!(newc.filesize() == 0);
 
     let bytes = newc.to_bytes().unwrap();
     assert!(bytes.len() > 0);
 
     let (_rest, parsed) = NewcHeader::from_bytes((bytes.as_ref(), 0)).unwrap();
-    assert!(parsed.name() == "test.txt");
+    assert!(parsed.name() == Some("test.txt"));
     assert!(parsed.ino() == 42);
 }
 
@@ -66,21 +65,21 @@ pub fn test_odc_header_creation() {
         rdev: Some(0),
         rdevmajor: None,
         rdevminor: None,
-        name: "script.sh".to_string(),
+        name: "script.sh".into(),
     };
 
-    let odc = OdcHeader::from_header(header, 10);
+    let odc = OdcHeader::from_header(header, 10).unwrap();
 
     assert!(odc.ino() == 100);
     assert!(odc.mode() == 0o100755);
-    assert!(odc.name() == "script.sh");
+    assert!(odc.name() == Some("script.sh"));
     assert!(odc.filesize() == 10);
 
     let bytes = odc.to_bytes().unwrap();
     assert!(bytes.len() > 0);
 
     let (_rest, parsed) = OdcHeader::from_bytes((bytes.as_ref(), 0)).unwrap();
-    assert!(parsed.name() == "script.sh");
+    assert!(parsed.name() == Some("script.sh"));
     assert!(parsed.filesize() == 10);
 }
 
@@ -90,7 +89,7 @@ pub fn test_archive_writer() {
     let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(cursor));
 
     let file_header = Header {
-        name: "hello.txt".to_string(),
+        name: "hello.txt".into(),
         mode: 0o100644,
         ..Header::default()
     };
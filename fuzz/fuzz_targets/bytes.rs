@@ -1,11 +1,16 @@
 #![no_main]
 
 use libfuzzer_sys::fuzz_target;
-use librarium::{ArchiveReader, NewcHeader};
+use librarium::{AnyHeader, ArchiveReader, CpioReader};
 
 fuzz_target!(|data: Vec<u8>| {
     let mut reader = std::io::Cursor::new(data);
 
     // doesn't crash
-    ArchiveReader::<NewcHeader>::from_reader_with_offset(&mut reader, 0);
+    if let Ok(mut archive) = ArchiveReader::<AnyHeader>::from_reader_with_offset(&mut reader, 0) {
+        for object in &archive.objects.inner {
+            let mut out = std::io::Cursor::new(vec![]);
+            let _ = archive.reader.extract_data(object, &mut out);
+        }
+    }
 });
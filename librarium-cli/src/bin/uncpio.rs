@@ -1,10 +1,13 @@
-use std::fs::{self, File, OpenOptions};
+use std::fs::File;
 use std::io::SeekFrom;
 use std::io::{BufReader, Seek};
 use std::path::{Path, PathBuf};
 
 use clap::{Parser, ValueEnum};
-use librarium::{ArchiveReader, CpioHeader, CpioReader, NewcHeader, OdcHeader};
+use librarium::{
+    ArchiveReader, BinHeader, CpioError, CpioHeader, CrcHeader, Extractor, MultiArchiveReader,
+    NewcHeader, Object, OdcHeader, ReadSeek, segment,
+};
 use log::{error, info};
 
 use clap::builder::styling::*;
@@ -23,6 +26,8 @@ pub fn styles() -> clap::builder::Styles {
 enum Format {
     Odc,
     Newc,
+    Crc,
+    Bin,
 }
 
 /// tool to extract and list cpio filesystems
@@ -37,8 +42,10 @@ struct Args {
     /// cpio path
     archive: PathBuf,
 
-    // #[arg(short, long, default_value_t = "newc")]
-    format: Format,
+    /// Format of archive. If not given, the format of each header is detected, compressed data
+    /// is decompressed, and all concatenated archives are extracted
+    #[arg(short, long)]
+    format: Option<Format>,
 
     /// Skip BYTES at the start of FILESYSTEM
     #[arg(short, long, default_value_t = 0, name = "BYTES")]
@@ -57,58 +64,63 @@ fn main() {
     file.seek(SeekFrom::Start(args.offset)).unwrap();
 
     // Extract all
+    let dest = &args.dest;
     match args.format {
-        Format::Odc => {
-            let mut archive: ArchiveReader<OdcHeader> =
-                match ArchiveReader::from_reader_with_offset(&mut file, args.offset) {
-                    Ok(a) => a,
-                    Err(e) => {
-                        error!("could not read archive: {e}");
-                        return;
-                    }
-                };
-            let len = archive.objects.inner.len();
-            for object in &archive.objects.inner[..len - 1] {
-                let filepath = Path::new(&args.dest).join(object.header.name());
-
-                info!("extracting: {:?} -> {:02x?}", object.header.name(), filepath);
-                if object.header.filesize() != 0 {
-                    let _ = fs::create_dir_all(filepath.parent().unwrap());
-                    let mut out = OpenOptions::new()
-                        .write(true)
-                        .create(true)
-                        .truncate(true)
-                        .open(filepath)
-                        .unwrap();
-                    archive.reader.extract_data(object, &mut out).unwrap();
+        Some(Format::Odc) => extract::<OdcHeader>(
+            ArchiveReader::from_reader_with_offset(&mut file, args.offset),
+            dest,
+        ),
+        Some(Format::Newc) => extract::<NewcHeader>(
+            ArchiveReader::from_reader_with_offset(&mut file, args.offset),
+            dest,
+        ),
+        Some(Format::Crc) => extract::<CrcHeader>(
+            ArchiveReader::from_reader_with_offset(&mut file, args.offset),
+            dest,
+        ),
+        Some(Format::Bin) => extract::<BinHeader>(
+            ArchiveReader::from_reader_with_offset(&mut file, args.offset),
+            dest,
+        ),
+        None => match MultiArchiveReader::from_reader_with_offset(&mut file, args.offset) {
+            Ok(mut archive) => {
+                for s in &archive.segments {
+                    info!("{:?} archive at {:#x} ({:?})", s.format, s.offset, s.compression);
                 }
+                extract_objects(&mut archive.reader, segment::merge(&archive.segments), dest);
             }
+            Err(e) => error!("could not read archive: {e}"),
+        },
+    }
+}
+
+fn extract<C: CpioHeader>(archive: Result<ArchiveReader<C>, CpioError>, dest: &Path) {
+    let mut archive = match archive {
+        Ok(a) => a,
+        Err(e) => {
+            error!("could not read archive: {e}");
+            return;
         }
-        Format::Newc => {
-            let mut archive: ArchiveReader<NewcHeader> =
-                match ArchiveReader::from_reader_with_offset(&mut file, args.offset) {
-                    Ok(a) => a,
-                    Err(e) => {
-                        error!("could not read archive: {e}");
-                        return;
-                    }
-                };
-            let len = archive.objects.inner.len();
-            for object in &archive.objects.inner[..len - 1] {
-                let filepath = Path::new(&args.dest).join(object.header.name());
+    };
+    extract_objects(&mut archive.reader, &archive.objects.inner, dest);
+}
 
-                info!("extracting: {:?} -> {:02x?}", object.header.name(), filepath);
-                if object.header.filesize() != 0 {
-                    let _ = fs::create_dir_all(filepath.parent().unwrap());
-                    let mut out = OpenOptions::new()
-                        .write(true)
-                        .create(true)
-                        .truncate(true)
-                        .open(filepath)
-                        .unwrap();
-                    archive.reader.extract_data(object, &mut out).unwrap();
-                }
+fn extract_objects<'a, C: CpioHeader + 'a>(
+    reader: &mut impl ReadSeek,
+    objects: impl IntoIterator<Item = &'a Object<C>>,
+    dest: &Path,
+) {
+    let extractor = Extractor::new(dest).skip_unpermitted(true);
+    match extractor.extract(reader, objects) {
+        Ok(rejected) => {
+            for r in rejected {
+                error!(
+                    "rejected unsafe entry {:?}: {:?}",
+                    String::from_utf8_lossy(&r.name),
+                    r.reason
+                );
             }
         }
+        Err(e) => error!("could not extract archive: {e}"),
     }
 }
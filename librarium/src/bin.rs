@@ -0,0 +1,247 @@
+use crate::cpio_header::{fit, pack_dev, read_name, trim_nul, with_nul};
+use crate::{CpioError, CpioHeader, Format, Header};
+use deku::ctx::Endian;
+use deku::prelude::*;
+use no_std_io2::io::{Read, Seek, Write};
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+const BIN_MAGIC: u16 = 0o070707;
+
+/// Old binary format, written as 16-bit words in either byte order
+///
+/// The byte order is detected from the magic when reading, and the byte order of the
+/// header is kept when writing a read header.
+#[derive(DekuWrite, DekuRead, Debug)]
+pub struct BinHeader {
+    #[deku(
+        reader = "Self::read_magic(deku::reader)",
+        writer = "Self::write_magic(deku::writer, *endian)"
+    )]
+    endian: Endian,
+    #[deku(endian = "*endian")]
+    dev: u16,
+    #[deku(endian = "*endian")]
+    ino: u16,
+    #[deku(endian = "*endian")]
+    mode: u16,
+    #[deku(endian = "*endian")]
+    uid: u16,
+    #[deku(endian = "*endian")]
+    gid: u16,
+    #[deku(endian = "*endian")]
+    nlink: u16,
+    #[deku(endian = "*endian")]
+    rdev: u16,
+    /// Most significant 16 bits first
+    #[deku(endian = "*endian")]
+    mtime: [u16; 2],
+    #[deku(endian = "*endian")]
+    namesize: u16,
+    /// Most significant 16 bits first
+    #[deku(endian = "*endian")]
+    filesize: [u16; 2],
+    #[deku(reader = "read_name(deku::reader, *namesize as usize)")]
+    name: Vec<u8>,
+    #[deku(count = "pad_to_2(*namesize as usize)")]
+    name_pad: Vec<u8>,
+}
+
+impl BinHeader {
+    fn read_magic<R: Read + Seek>(reader: &mut Reader<R>) -> Result<Endian, DekuError> {
+        let magic = <[u8; 2]>::from_reader_with_ctx(reader, ())?;
+        if u16::from_le_bytes(magic) == BIN_MAGIC {
+            Ok(Endian::Little)
+        } else if u16::from_be_bytes(magic) == BIN_MAGIC {
+            Ok(Endian::Big)
+        } else {
+            Err(deku::deku_error!(DekuError::Assertion, "invalid bin magic"))
+        }
+    }
+
+    fn write_magic<W: Write + Seek>(
+        writer: &mut Writer<W>,
+        endian: Endian,
+    ) -> Result<(), DekuError> {
+        BIN_MAGIC.to_writer(writer, endian)
+    }
+}
+
+impl CpioHeader for BinHeader {
+    fn from_header(header: Header, filesize: u64) -> Result<Self, CpioError> {
+        let name_bytes = with_nul(header.name)?;
+        let name_len = name_bytes.len();
+        let rdev = header.rdev.unwrap_or_else(|| {
+            pack_dev(header.rdevmajor.unwrap_or(0), header.rdevminor.unwrap_or(0))
+        });
+
+        Ok(Self {
+            endian: Endian::Little,
+            dev: fit(Format::Bin, "dev", header.dev.unwrap_or(0))?,
+            ino: fit(Format::Bin, "ino", header.ino)?,
+            mode: fit(Format::Bin, "mode", header.mode)?,
+            uid: fit(Format::Bin, "uid", header.uid)?,
+            gid: fit(Format::Bin, "gid", header.gid)?,
+            nlink: fit(Format::Bin, "nlink", header.nlink)?,
+            rdev: fit(Format::Bin, "rdev", rdev)?,
+            mtime: split(fit(Format::Bin, "mtime", header.mtime)?),
+            namesize: fit(Format::Bin, "namesize", name_len as u64)?,
+            filesize: split(fit(Format::Bin, "filesize", filesize)?),
+            name: name_bytes,
+            name_pad: vec![0; pad_to_2(name_len)],
+        })
+    }
+
+    fn as_header(&self) -> Header {
+        Header {
+            ino: self.ino(),
+            mode: self.mode(),
+            uid: self.uid(),
+            gid: self.gid(),
+            nlink: self.nlink(),
+            mtime: self.mtime(),
+            dev: self.dev(),
+            devmajor: self.devmajor(),
+            devminor: self.devminor(),
+            rdev: self.rdev(),
+            rdevmajor: self.rdevmajor(),
+            rdevminor: self.rdevminor(),
+            name: self.name_bytes().to_vec(),
+        }
+    }
+
+    fn ino(&self) -> u32 {
+        self.ino.into()
+    }
+
+    fn mode(&self) -> u32 {
+        self.mode.into()
+    }
+
+    fn uid(&self) -> u32 {
+        self.uid.into()
+    }
+
+    fn gid(&self) -> u32 {
+        self.gid.into()
+    }
+
+    fn nlink(&self) -> u32 {
+        self.nlink.into()
+    }
+
+    fn mtime(&self) -> u64 {
+        join(self.mtime).into()
+    }
+
+    fn filesize(&self) -> u64 {
+        join(self.filesize).into()
+    }
+
+    fn set_ino(&mut self, ino: u32) -> Result<(), CpioError> {
+        self.ino = fit(Format::Bin, "ino", ino)?;
+        Ok(())
+    }
+
+    fn set_nlink(&mut self, nlink: u32) -> Result<(), CpioError> {
+        self.nlink = fit(Format::Bin, "nlink", nlink)?;
+        Ok(())
+    }
+
+    fn set_filesize(&mut self, filesize: u64) -> Result<(), CpioError> {
+        self.filesize = split(fit(Format::Bin, "filesize", filesize)?);
+        Ok(())
+    }
+
+    fn dev(&self) -> Option<u32> {
+        Some(self.dev.into())
+    }
+
+    fn devmajor(&self) -> Option<u32> {
+        None
+    }
+
+    fn devminor(&self) -> Option<u32> {
+        None
+    }
+
+    fn rdev(&self) -> Option<u32> {
+        Some(self.rdev.into())
+    }
+
+    fn rdevmajor(&self) -> Option<u32> {
+        None
+    }
+
+    fn rdevminor(&self) -> Option<u32> {
+        None
+    }
+
+    fn namesize(&self) -> u32 {
+        self.namesize.into()
+    }
+
+    fn check(&self) -> Option<u32> {
+        None
+    }
+
+    fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        trim_nul(&self.name)
+    }
+
+    fn data_pad(&self) -> usize {
+        pad_to_2((self.filesize() % 2) as usize)
+    }
+}
+
+/// pad out to a multiple of 2 bytes
+fn pad_to_2(len: usize) -> usize {
+    len % 2
+}
+
+/// 32 bit value as 16 bit words, most significant first
+fn split(value: u32) -> [u16; 2] {
+    [(value >> 16) as u16, value as u16]
+}
+
+fn join(words: [u16; 2]) -> u32 {
+    (u32::from(words[0]) << 16) | u32::from(words[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endian() {
+        let header = Header { name: "a".into(), mtime: 0x1234_5678, ..Header::default() };
+        let mut bin = BinHeader::from_header(header, 0x0001_0002).unwrap();
+        let bytes = bin.to_bytes().unwrap();
+        assert_eq!(&bytes[..2], &[0xc7, 0x71]);
+        assert_eq!(&bytes[16..18], &[0x34, 0x12]);
+        assert_eq!(&bytes[18..20], &[0x78, 0x56]);
+        assert_eq!(bytes.len(), 28);
+
+        bin.set_endian(Endian::Big);
+        let bytes = bin.to_bytes().unwrap();
+        assert_eq!(&bytes[..2], &[0x71, 0xc7]);
+        assert_eq!(&bytes[22..26], &[0x00, 0x01, 0x00, 0x02]);
+
+        let (_, bin) = BinHeader::from_bytes((&bytes, 0)).unwrap();
+        assert_eq!(bin.endian(), Endian::Big);
+        assert_eq!(bin.mtime(), 0x1234_5678);
+        assert_eq!(bin.filesize(), 0x0001_0002);
+        assert_eq!(bin.name(), Some("a"));
+    }
+}
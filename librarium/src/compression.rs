@@ -0,0 +1,593 @@
+#[cfg(feature = "std")]
+extern crate std;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "std")]
+use std::{cell::RefCell, rc::Rc, vec, vec::Vec};
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use crate::format::peek_magic;
+#[cfg(feature = "std")]
+use crate::read_seek::ForwardReader;
+use crate::{CpioError, ReadSeek};
+
+/// Magic of the lz4 legacy frame format, as used by the Linux kernel
+#[cfg(feature = "lz4")]
+const LZ4_LEGACY_MAGIC: u32 = 0x184c_2102;
+
+/// Uncompressed size of each block of the lz4 legacy frame format
+#[cfg(feature = "lz4")]
+const LZ4_LEGACY_BLOCK_LEN: usize = 8 << 20;
+
+/// Compression of archives, as supported by the Linux kernel for initramfs images
+///
+/// Decompression and compression of each compression requires the feature of the same name.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Xz,
+    Lzma,
+    Zstd,
+    /// lz4 legacy frame format
+    Lz4,
+    Bzip2,
+}
+
+impl Compression {
+    /// Detect compression from the magic at the start of `bytes`
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x1f, 0x8b, ..] => Some(Self::Gzip),
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Self::Xz),
+            [0x5d, 0x00, 0x00, ..] => Some(Self::Lzma),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Self::Zstd),
+            [0x02, 0x21, 0x4c, 0x18, ..] => Some(Self::Lz4),
+            [b'B', b'Z', b'h', ..] => Some(Self::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Detect compression from the magic at the current position of `reader`, without advancing
+    /// it
+    pub fn detect<R: ReadSeek + ?Sized>(reader: &mut R) -> Result<Option<Self>, CpioError> {
+        let (magic, len) = peek_magic(reader)?;
+        Ok(Self::from_magic(&magic[..len]))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Compression {
+    /// Decompressed data of `reader`
+    ///
+    /// Returns [`CpioError::UnsupportedCompression`] if the feature of the compression isn't
+    /// enabled.
+    pub fn decoder<'a>(self, reader: impl Read + 'a) -> Result<Box<dyn Read + 'a>, CpioError> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+            #[cfg(feature = "xz")]
+            Self::Xz => Ok(Box::new(liblzma::read::XzDecoder::new_multi_decoder(reader))),
+            #[cfg(feature = "lzma")]
+            Self::Lzma => {
+                let stream =
+                    liblzma::stream::Stream::new_lzma_decoder(u64::MAX).map_err(io::Error::from)?;
+                Ok(Box::new(liblzma::read::XzDecoder::new_stream(reader, stream)))
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(Box::new(Lz4LegacyDecoder::new(reader))),
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => Ok(Box::new(bzip2::read::MultiBzDecoder::new(reader))),
+            #[allow(unreachable_patterns)]
+            _ => {
+                drop(reader);
+                Err(CpioError::UnsupportedCompression(self))
+            }
+        }
+    }
+
+    /// Compress data written to `writer` with compression `level`, or the default level of the
+    /// compression if `None`
+    ///
+    /// xz uses the CRC32 check, as required by the kernel. `level` isn't used by lz4.
+    ///
+    /// Returns an `InvalidInput` error if `level` isn't a level of the compression, `0..=9` for
+    /// gzip, xz and lzma, `1..=9` for bzip2 and the levels supported by the zstd library.
+    fn encoder<'a>(
+        self,
+        writer: impl Write + 'a,
+        level: Option<u32>,
+    ) -> Result<Box<dyn Encoder + 'a>, CpioError> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let level = self.check_level(level, 0..=9)?;
+                let level =
+                    level.map_or_else(flate2::Compression::default, flate2::Compression::new);
+                Ok(Box::new(flate2::write::GzEncoder::new(writer, level)))
+            }
+            #[cfg(feature = "xz")]
+            Self::Xz => {
+                let level = self.check_level(level, 0..=9)?;
+                let check = liblzma::stream::Check::Crc32;
+                let stream = liblzma::stream::Stream::new_easy_encoder(level.unwrap_or(6), check)
+                    .map_err(io::Error::from)?;
+                Ok(Box::new(liblzma::write::XzEncoder::new_stream(writer, stream)))
+            }
+            #[cfg(feature = "lzma")]
+            Self::Lzma => {
+                let level = self.check_level(level, 0..=9)?;
+                let options = liblzma::stream::LzmaOptions::new_preset(level.unwrap_or(6))
+                    .map_err(io::Error::from)?;
+                let stream =
+                    liblzma::stream::Stream::new_lzma_encoder(&options).map_err(io::Error::from)?;
+                Ok(Box::new(liblzma::write::XzEncoder::new_stream(writer, stream)))
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                // negative levels can't be given as `u32`
+                let max = zstd::compression_level_range().end().unsigned_abs();
+                let level = self.check_level(level, 0..=max)?;
+                let level = level.map_or(zstd::DEFAULT_COMPRESSION_LEVEL, |level| level as i32);
+                Ok(Box::new(zstd::stream::write::Encoder::new(writer, level)?))
+            }
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(Box::new(Lz4LegacyEncoder::new(writer))),
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => {
+                let level = match level {
+                    Some(level) => bzip2::Compression::try_new(level)
+                        .ok_or_else(|| self.invalid_level(level))?,
+                    None => bzip2::Compression::default(),
+                };
+                Ok(Box::new(bzip2::write::BzEncoder::new(writer, level)))
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                drop((writer, level));
+                Err(CpioError::UnsupportedCompression(self))
+            }
+        }
+    }
+
+    /// `level`, or an `InvalidInput` error if it isn't in `levels`
+    #[cfg(any(feature = "gzip", feature = "xz", feature = "lzma", feature = "zstd"))]
+    fn check_level(
+        self,
+        level: Option<u32>,
+        levels: core::ops::RangeInclusive<u32>,
+    ) -> Result<Option<u32>, CpioError> {
+        match level {
+            Some(level) if !levels.contains(&level) => Err(self.invalid_level(level)),
+            level => Ok(level),
+        }
+    }
+
+    #[cfg(any(
+        feature = "gzip",
+        feature = "xz",
+        feature = "lzma",
+        feature = "zstd",
+        feature = "bzip2"
+    ))]
+    fn invalid_level(self, level: u32) -> CpioError {
+        let msg = std::format!("invalid {self:?} compression level: {level}");
+        io::Error::new(io::ErrorKind::InvalidInput, msg).into()
+    }
+
+    /// Decompress all data of `reader` into memory
+    pub fn decompress(self, reader: impl Read) -> Result<Vec<u8>, CpioError> {
+        let mut bytes = vec![];
+        self.decoder(reader)?.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// `Write` that compresses data, finished after all data is written
+#[cfg(feature = "std")]
+trait Encoder: Write {
+    /// Write the end of the compressed data
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+#[cfg(feature = "gzip")]
+impl<W: Write> Encoder for flate2::write::GzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        flate2::write::GzEncoder::finish(*self).map(drop)
+    }
+}
+
+#[cfg(any(feature = "xz", feature = "lzma"))]
+impl<W: Write> Encoder for liblzma::write::XzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        liblzma::write::XzEncoder::finish(*self).map(drop)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<W: Write> Encoder for zstd::stream::write::Encoder<'_, W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        zstd::stream::write::Encoder::finish(*self).map(drop)
+    }
+}
+
+#[cfg(feature = "bzip2")]
+impl<W: Write> Encoder for bzip2::write::BzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        bzip2::write::BzEncoder::finish(*self).map(drop)
+    }
+}
+
+/// Compressed output of [`ArchiveWriter`](crate::ArchiveWriter)
+///
+/// Only seeking to the current position is supported, as used by deku.
+#[cfg(feature = "std")]
+pub(crate) struct CompressWriter<'a> {
+    /// `None` once finished
+    encoder: Option<Box<dyn Encoder + 'a>>,
+    /// Uncompressed bytes written
+    position: u64,
+}
+
+#[cfg(feature = "std")]
+impl<'a> CompressWriter<'a> {
+    pub(crate) fn new(
+        writer: impl Write + 'a,
+        compression: Compression,
+        level: Option<u32>,
+    ) -> Result<Self, CpioError> {
+        let encoder = compression.encoder(writer, level)?;
+        Ok(Self { encoder: Some(encoder), position: 0 })
+    }
+
+    /// Write the end of the compressed data, writing after this returns an error
+    pub(crate) fn finish(&mut self) -> io::Result<()> {
+        match self.encoder.take() {
+            Some(encoder) => encoder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    fn encoder(&mut self) -> io::Result<&mut Box<dyn Encoder + 'a>> {
+        self.encoder.as_mut().ok_or_else(|| io::Error::other("compressed data already finished"))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for CompressWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.encoder()?.write(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder()?.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Seek for CompressWriter<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Start(n) if n == self.position => Ok(n),
+            SeekFrom::Current(0) => Ok(self.position),
+            _ => Err(io::ErrorKind::Unsupported.into()),
+        }
+    }
+}
+
+/// Replace the data of `reader` from `offset` with the decompressed data
+///
+/// The data before `offset` is kept, so offsets before `offset` are unchanged. The data is
+/// decompressed when read, see [`DecompressReader`]: reading forward is cheap, but every seek
+/// backward decompresses again from `offset`, and seeking from the end decompresses all data the
+/// first time.
+#[cfg(feature = "std")]
+pub(crate) fn decompress_at<'b>(
+    reader: Box<dyn ReadSeek + 'b>,
+    offset: u64,
+    compression: Compression,
+) -> Result<Box<dyn ReadSeek + 'b>, CpioError> {
+    let reader = DecompressReader::new(reader, offset, compression)?;
+    log::debug!("decompressing {compression:?} data at {offset:#x}");
+
+    Ok(Box::new(reader))
+}
+
+/// Data of a reader with the data from `offset` decompressed when read
+///
+/// Only the data being read is kept in memory. Seeking forward decompresses and discards the
+/// data before the new position, and seeking backward starts decompressing again from `offset`.
+/// The length is known once the end is reached, seeking from the end before that decompresses all
+/// data to find it.
+#[cfg(feature = "std")]
+struct DecompressReader<'b> {
+    source: Rc<RefCell<Box<dyn ReadSeek + 'b>>>,
+    offset: u64,
+    compression: Compression,
+    decoder: ForwardReader<Box<dyn Read + 'b>>,
+    pos: u64,
+    /// Length of the decompressed data, once known
+    len: Option<u64>,
+}
+
+#[cfg(feature = "std")]
+impl<'b> DecompressReader<'b> {
+    fn new(
+        reader: Box<dyn ReadSeek + 'b>,
+        offset: u64,
+        compression: Compression,
+    ) -> Result<Self, CpioError> {
+        let source = Rc::new(RefCell::new(reader));
+        let decoder = Self::decoder(&source, offset, compression)?;
+        Ok(Self { source, offset, compression, decoder, pos: 0, len: None })
+    }
+
+    fn decoder(
+        source: &Rc<RefCell<Box<dyn ReadSeek + 'b>>>,
+        offset: u64,
+        compression: Compression,
+    ) -> Result<ForwardReader<Box<dyn Read + 'b>>, CpioError> {
+        let compressed = SourceReader { source: source.clone(), pos: offset };
+        Ok(ForwardReader::new(compression.decoder(compressed)?))
+    }
+
+    /// Seek `decoder` to `pos` of the decompressed data, returning `false` if it is after the end
+    fn seek_decoder(&mut self, pos: u64) -> io::Result<bool> {
+        if self.len.is_some_and(|len| self.offset + pos >= len) {
+            return Ok(false);
+        }
+        match self.decoder.seek(SeekFrom::Start(pos)) {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                self.len = Some(self.offset + self.decoder.position());
+                Ok(false)
+            }
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+                self.decoder = Self::decoder(&self.source, self.offset, self.compression)
+                    .map_err(io::Error::other)?;
+                self.seek_decoder(pos)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Length of the data, decompressing all data if not yet known
+    fn len(&mut self) -> io::Result<u64> {
+        if let Some(len) = self.len {
+            return Ok(len);
+        }
+        let mut buf = vec![0; 0x2000];
+        while self.decoder.read(&mut buf)? != 0 {}
+        let len = self.offset + self.decoder.position();
+        self.len = Some(len);
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Read for DecompressReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = if self.pos < self.offset {
+            let len = buf.len().min((self.offset - self.pos) as usize);
+            let mut source = SourceReader { source: self.source.clone(), pos: self.pos };
+            source.read(&mut buf[..len])?
+        } else if self.seek_decoder(self.pos - self.offset)? {
+            let n = self.decoder.read(buf)?;
+            if n == 0 && !buf.is_empty() {
+                self.len = Some(self.pos);
+            }
+            n
+        } else {
+            0
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Seek for DecompressReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.len()?.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        self.pos = pos.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        Ok(self.pos)
+    }
+}
+
+/// `Read` of `source` from `pos`, sharing `source` with [`DecompressReader`]
+#[cfg(feature = "std")]
+struct SourceReader<'b> {
+    source: Rc<RefCell<Box<dyn ReadSeek + 'b>>>,
+    pos: u64,
+}
+
+#[cfg(feature = "std")]
+impl Read for SourceReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut source = self.source.borrow_mut();
+        source.seek(SeekFrom::Start(self.pos))?;
+        let n = source.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// Without `std`, decompression isn't supported
+#[cfg(not(feature = "std"))]
+pub(crate) fn decompress_at<'b>(
+    _: Box<dyn ReadSeek + 'b>,
+    _: u64,
+    compression: Compression,
+) -> Result<Box<dyn ReadSeek + 'b>, CpioError> {
+    Err(CpioError::UnsupportedCompression(compression))
+}
+
+/// Decoder of the lz4 legacy frame format
+///
+/// Each frame starts with [`LZ4_LEGACY_MAGIC`], followed by blocks of the compressed size as
+/// `u32` and the lz4 block. Like the kernel, a new frame can start in place of a block, and a
+/// block size of 0 ends the data.
+#[cfg(feature = "lz4")]
+struct Lz4LegacyDecoder<R: Read> {
+    reader: R,
+    compressed: Vec<u8>,
+    block: Vec<u8>,
+    pos: usize,
+    started: bool,
+    finished: bool,
+}
+
+#[cfg(feature = "lz4")]
+impl<R: Read> Lz4LegacyDecoder<R> {
+    fn new(reader: R) -> Self {
+        Self { reader, compressed: vec![], block: vec![], pos: 0, started: false, finished: false }
+    }
+
+    /// Read next `u32`, or `None` at the end of `reader`
+    fn read_u32(&mut self) -> io::Result<Option<u32>> {
+        let mut bytes = [0; 4];
+        let mut len = 0;
+        while len < bytes.len() {
+            match self.reader.read(&mut bytes[len..]) {
+                Ok(0) if len == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Some(u32::from_le_bytes(bytes)))
+    }
+
+    /// Decompress next block into `block`, returning `false` at the end of data
+    fn next_block(&mut self) -> io::Result<bool> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        if !self.started {
+            if self.read_u32()? != Some(LZ4_LEGACY_MAGIC) {
+                return Err(invalid("invalid lz4 legacy magic"));
+            }
+            self.started = true;
+        }
+
+        let len = loop {
+            match self.read_u32()? {
+                None | Some(0) => return Ok(false),
+                Some(LZ4_LEGACY_MAGIC) => continue,
+                Some(len) => break len as usize,
+            }
+        };
+        if len > lz4_flex::block::get_maximum_output_size(LZ4_LEGACY_BLOCK_LEN) {
+            return Err(invalid("lz4 legacy block too large"));
+        }
+
+        self.compressed.resize(len, 0);
+        self.reader.read_exact(&mut self.compressed)?;
+        self.block.resize(LZ4_LEGACY_BLOCK_LEN, 0);
+        let len = lz4_flex::block::decompress_into(&self.compressed, &mut self.block)
+            .map_err(|_| invalid("invalid lz4 block"))?;
+        self.block.truncate(len);
+        self.pos = 0;
+
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "lz4")]
+impl<R: Read> Read for Lz4LegacyDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.block.len() {
+            if self.finished || !self.next_block()? {
+                self.finished = true;
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.block.len() - self.pos);
+        buf[..n].copy_from_slice(&self.block[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Encoder of the lz4 legacy frame format, see [`Lz4LegacyDecoder`]
+#[cfg(feature = "lz4")]
+struct Lz4LegacyEncoder<W: Write> {
+    writer: W,
+    /// Uncompressed data of the current block
+    block: Vec<u8>,
+    started: bool,
+}
+
+#[cfg(feature = "lz4")]
+impl<W: Write> Lz4LegacyEncoder<W> {
+    fn new(writer: W) -> Self {
+        Self { writer, block: vec![], started: false }
+    }
+
+    /// Compress and write `block`
+    fn write_block(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.writer.write_all(&LZ4_LEGACY_MAGIC.to_le_bytes())?;
+            self.started = true;
+        }
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        let compressed = lz4_flex::block::compress(&self.block);
+        self.writer.write_all(&(compressed.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&compressed)?;
+        self.block.clear();
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "lz4")]
+impl<W: Write> Write for Lz4LegacyEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.block.len() == LZ4_LEGACY_BLOCK_LEN {
+            self.write_block()?;
+        }
+        let n = buf.len().min(LZ4_LEGACY_BLOCK_LEN - self.block.len());
+        self.block.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(feature = "lz4")]
+impl<W: Write> Encoder for Lz4LegacyEncoder<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.write_block()?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_magic() {
+        assert_eq!(Compression::from_magic(&[0x1f, 0x8b, 0x08]), Some(Compression::Gzip));
+        assert_eq!(Compression::from_magic(b"\xfd7zXZ\x00"), Some(Compression::Xz));
+        assert_eq!(Compression::from_magic(&[0x02, 0x21, 0x4c, 0x18]), Some(Compression::Lz4));
+        assert_eq!(Compression::from_magic(b"070701"), None);
+        assert_eq!(Compression::from_magic(&[0x1f]), None);
+    }
+}
@@ -1,18 +1,52 @@
+use deku::ctx::Endian;
 use deku::prelude::*;
+use no_std_io2::io::{Read, Seek};
 
-use crate::Header;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String, vec::Vec};
+#[cfg(feature = "std")]
+extern crate std;
+
+use crate::error::MISSING_NUL;
+use crate::mode::{FileType, Permissions};
+use crate::{CpioError, Format, Header};
 
 /// Common information between types of cpio headers
 pub trait CpioHeader: for<'a> DekuReader<'a> + DekuWriter {
     fn as_header(&self) -> Header;
-    fn from_header(header: Header, filesize: u64) -> Self;
+    /// Create header from `header` with `filesize`
+    ///
+    /// Returns [`CpioError::FieldOverflow`] if a value doesn't fit in the field of the format.
+    fn from_header(header: Header, filesize: u64) -> Result<Self, CpioError>
+    where
+        Self: Sized;
+    /// Create header from `header` with `filesize`, in the same format as `self`
+    ///
+    /// Only differs from [`Self::from_header`] for headers that can be of more than one format,
+    /// such as [`AnyHeader`](crate::AnyHeader).
+    fn new_like(&self, header: Header, filesize: u64) -> Result<Self, CpioError>
+    where
+        Self: Sized,
+    {
+        Self::from_header(header, filesize)
+    }
     fn ino(&self) -> u32;
     fn mode(&self) -> u32;
     fn uid(&self) -> u32;
     fn gid(&self) -> u32;
     fn nlink(&self) -> u32;
-    fn mtime(&self) -> u32;
-    fn filesize(&self) -> u32;
+    fn mtime(&self) -> u64;
+    fn filesize(&self) -> u64;
+    /// File type of `mode`, `None` if not set or unknown
+    fn file_type(&self) -> Option<FileType> {
+        FileType::from_mode(self.mode())
+    }
+    /// Permission bits of `mode`
+    fn permissions(&self) -> Permissions {
+        Permissions::from_bits(self.mode())
+    }
     /// Device number of device creating file
     fn dev(&self) -> Option<u32>;
     /// Device major number of device creating file
@@ -23,9 +57,122 @@ pub trait CpioHeader: for<'a> DekuReader<'a> + DekuWriter {
     fn rdev(&self) -> Option<u32>;
     fn rdevmajor(&self) -> Option<u32>;
     fn rdevminor(&self) -> Option<u32>;
+    /// Device number `(major, minor)` of character and block devices, `None` for other file types
+    ///
+    /// Decoded from `rdevmajor` and `rdevminor`, or from `rdev` with an 8 bit minor number for
+    /// formats with a single field.
+    fn device(&self) -> Option<(u32, u32)> {
+        match self.file_type() {
+            Some(FileType::CharDevice | FileType::BlockDevice) => Some(rdev_numbers(self)),
+            _ => None,
+        }
+    }
 
     fn namesize(&self) -> u32;
     fn check(&self) -> Option<u32>;
-    fn name(&self) -> &str;
+    /// `check` contains a checksum of the file data, see [`checksum`](crate::crc::checksum)
+    fn has_checksum(&self) -> bool {
+        false
+    }
+    /// Set checksum of file data, ignored by formats without a checksum
+    fn set_check(&mut self, _check: u32) {}
+    /// Byte order of header, little endian for ASCII formats
+    fn endian(&self) -> Endian {
+        Endian::Little
+    }
+    /// Set byte order of header, ignored by ASCII formats
+    fn set_endian(&mut self, _endian: Endian) {}
+    /// Set inode number
+    ///
+    /// Returns [`CpioError::FieldOverflow`] if `ino` doesn't fit in the field of the format.
+    fn set_ino(&mut self, ino: u32) -> Result<(), CpioError>;
+    /// Set number of links to the file
+    ///
+    /// Returns [`CpioError::FieldOverflow`] if `nlink` doesn't fit in the field of the format.
+    fn set_nlink(&mut self, nlink: u32) -> Result<(), CpioError>;
+    /// Set size of the file data, the data itself isn't changed
+    ///
+    /// Returns [`CpioError::FieldOverflow`] if `filesize` doesn't fit in the field of the format.
+    fn set_filesize(&mut self, filesize: u64) -> Result<(), CpioError>;
+    /// Only the last hard link to a file contains the data, earlier links have a `filesize` of 0
+    fn data_on_last_link(&self) -> bool {
+        false
+    }
+    /// Name as bytes, without the NUL terminator
+    fn name_bytes(&self) -> &[u8];
+    /// Name as UTF-8, or `None` if the name isn't valid UTF-8, see [`Self::name_bytes`]
+    fn name(&self) -> Option<&str> {
+        core::str::from_utf8(self.name_bytes()).ok()
+    }
+    /// Name for display, with invalid UTF-8 replaced by `U+FFFD`
+    #[cfg(feature = "alloc")]
+    fn name_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.name_bytes())
+    }
+    /// Name as `OsStr`, for paths on the filesystem
+    #[cfg(all(feature = "std", unix))]
+    fn name_os_str(&self) -> &std::ffi::OsStr {
+        std::os::unix::ffi::OsStrExt::from_bytes(self.name_bytes())
+    }
     fn data_pad(&self) -> usize;
 }
+
+/// `value` of `field` converted to `T`, or [`CpioError::FieldOverflow`] if it doesn't fit in
+/// `format`
+pub(crate) fn fit<T: TryFrom<u64>>(
+    format: Format,
+    field: &'static str,
+    value: impl Into<u64>,
+) -> Result<T, CpioError> {
+    let value = value.into();
+    T::try_from(value).map_err(|_| CpioError::FieldOverflow { format, field, value })
+}
+
+/// Device number with the `major` and an 8 bit `minor` number, as stored in the single `dev` and
+/// `rdev` fields of odc and bin headers
+pub(crate) fn pack_dev(major: u32, minor: u32) -> u32 {
+    (major << 8) | (minor & 0xff)
+}
+
+/// Major and minor number of `rdev` of `header`
+pub(crate) fn rdev_numbers<C: CpioHeader + ?Sized>(header: &C) -> (u32, u32) {
+    match (header.rdevmajor(), header.rdevminor(), header.rdev()) {
+        (Some(major), Some(minor), _) => (major, minor),
+        (_, _, Some(rdev)) => (rdev >> 8, rdev & 0xff),
+        _ => (0, 0),
+    }
+}
+
+/// Read name of `namesize` bytes, which must be NUL terminated
+pub(crate) fn read_name<R: Read + Seek>(
+    reader: &mut Reader<R>,
+    namesize: usize,
+) -> Result<Vec<u8>, DekuError> {
+    // read byte by byte, an invalid namesize shouldn't allocate more than what is in the archive
+    let mut name = Vec::new();
+    for _ in 0..namesize {
+        name.push(u8::from_reader_with_ctx(reader, ())?);
+    }
+
+    if name.last() != Some(&0) {
+        return Err(deku::deku_error!(DekuError::Parse, MISSING_NUL));
+    }
+
+    Ok(name)
+}
+
+/// `name` with the NUL terminator, or [`CpioError::InvalidName`] if it contains a NUL byte that
+/// would end the name early when read
+pub(crate) fn with_nul(mut name: Vec<u8>) -> Result<Vec<u8>, CpioError> {
+    if name.contains(&0) {
+        return Err(CpioError::InvalidName);
+    }
+    name.push(0);
+    Ok(name)
+}
+
+/// Name from bytes, up to the first NUL
+pub(crate) fn trim_nul(name: &[u8]) -> &[u8] {
+    let len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+    &name[..len]
+}
@@ -0,0 +1,25 @@
+use crate::newc::Svr4Header;
+
+/// New ASCII format with checksum, also known as "SVR4 with CRC"
+///
+/// Same layout as [`NewcHeader`](crate::NewcHeader), but `check` contains the sum of all bytes of
+/// the file data.
+pub type CrcHeader = Svr4Header<true>;
+
+/// Add `bytes` to running checksum `sum`, as stored in the `check` field of [`CrcHeader`]
+pub fn checksum(sum: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(sum, |sum, b| sum.wrapping_add(u32::from(*b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(0, b""), 0);
+        assert_eq!(checksum(0, b"a\n"), 0x61 + 0x0a);
+        assert_eq!(checksum(checksum(0, b"a"), b"\n"), checksum(0, b"a\n"));
+        assert_eq!(checksum(u32::MAX, &[2]), 1);
+    }
+}
@@ -0,0 +1,210 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+
+use core::cell::RefCell;
+use core::fmt::Debug;
+
+use no_std_io2::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+use crate::{
+    ArchiveReader, ArchiveWriter, CpioError, CpioHeader, Data, Header, ReadSeek, TRAILER, link,
+    normalize_path,
+};
+
+/// Edit the objects of an existing archive and write them as a new archive
+///
+/// Objects are kept in archive order, and changes are recorded until [`Self::push_to`], which
+/// copies the data of unchanged objects straight from the reader of the source archive.
+///
+/// Paths are compared after [`normalize_path`]. If more than one object has the same path, the
+/// last is changed, as returned by [`ArchiveReader::get`].
+///
+/// Hard links keep their data if the link containing the data is removed, and are written as
+/// links again by [`ArchiveWriter::write`].
+///
+/// # Example
+/// Replace `bin/busybox` of `initramfs.cpio`.
+/// ```rust, no_run
+/// # use librarium::{ArchiveEditor, ArchiveReader, ArchiveWriter, NewcHeader};
+/// # use std::fs::File;
+/// let archive = ArchiveReader::<NewcHeader>::from_reader(File::open("initramfs.cpio").unwrap())
+///     .unwrap();
+/// let mut editor = ArchiveEditor::new(archive).unwrap();
+/// editor.replace("bin/busybox", File::open("busybox").unwrap());
+/// editor.remove("etc/motd");
+///
+/// let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(File::create("out.cpio").unwrap()));
+/// editor.push_to(&mut writer).unwrap();
+/// writer.write().unwrap();
+/// ```
+pub struct ArchiveEditor {
+    source: Rc<RefCell<Box<dyn ReadSeek>>>,
+    entries: Vec<Entry>,
+}
+
+/// Object of an [`ArchiveEditor`]
+struct Entry {
+    header: Header,
+    data: EntryData,
+}
+
+enum EntryData {
+    /// Data of `filesize` bytes at `offset` in the source archive
+    Source {
+        offset: u64,
+        filesize: u64,
+    },
+    Reader(Box<dyn ReadSeek>),
+}
+
+impl ArchiveEditor {
+    /// Create new `ArchiveEditor` with the objects of `archive`, excluding the Trailer entry
+    ///
+    /// The reader of `archive` is kept to copy the data of the objects when written, so it must
+    /// own its data, such as a `File` or a `Cursor<Vec<u8>>`.
+    ///
+    /// Returns an `InvalidInput` error if an object wasn't read from the archive, such as an
+    /// object added to the objects of `archive` with [`Data::Reader`].
+    pub fn new<C: CpioHeader>(archive: ArchiveReader<'static, C>) -> Result<Self, CpioError> {
+        let objects = &archive.objects.inner;
+        let entries = objects
+            .iter()
+            .filter(|object| object.header.name_bytes() != TRAILER.as_bytes())
+            .map(|object| {
+                // empty hard links get the data of the link containing it
+                let data = link::resolve(objects, object);
+                let Data::Offset(offset) = data.data else {
+                    return Err(CpioError::StdIo(ErrorKind::InvalidInput.into()));
+                };
+                let data = EntryData::Source { offset, filesize: data.header.filesize() };
+                Ok(Entry { header: object.header.as_header(), data })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { source: Rc::new(RefCell::new(archive.reader)), entries })
+    }
+
+    /// Headers of all objects, in the order written
+    pub fn headers(&self) -> impl Iterator<Item = &Header> {
+        self.entries.iter().map(|entry| &entry.header)
+    }
+
+    /// Header of object at `path`
+    pub fn get(&self, path: impl AsRef<[u8]>) -> Option<&Header> {
+        self.find(path.as_ref()).map(|i| &self.entries[i].header)
+    }
+
+    /// Header of object at `path`, to change the metadata of the object
+    pub fn get_mut(&mut self, path: impl AsRef<[u8]>) -> Option<&mut Header> {
+        self.find(path.as_ref()).map(|i| &mut self.entries[i].header)
+    }
+
+    /// Remove all objects at `path`, returning `false` if there are none
+    ///
+    /// Objects below a directory at `path` aren't removed.
+    pub fn remove(&mut self, path: impl AsRef<[u8]>) -> bool {
+        let path = normalize_path(path);
+        let len = self.entries.len();
+        self.entries.retain(|entry| normalize_path(&entry.header.name) != path);
+        self.entries.len() != len
+    }
+
+    /// Rename object at `path` to `name`, returning `false` if there is no object at `path`
+    ///
+    /// Objects below a directory at `path` aren't renamed.
+    pub fn rename(&mut self, path: impl AsRef<[u8]>, name: impl Into<Vec<u8>>) -> bool {
+        match self.get_mut(path) {
+            Some(header) => {
+                header.name = name.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace data of object at `path` with `data`, returning `false` if there is no object
+    /// at `path`
+    ///
+    /// A hard link with replaced data is written as a separate file with an `nlink` of 1, the
+    /// other links keep the original data.
+    pub fn replace(&mut self, path: impl AsRef<[u8]>, data: impl ReadSeek + 'static) -> bool {
+        let Some(i) = self.find(path.as_ref()) else {
+            return false;
+        };
+        let entry = &mut self.entries[i];
+        if link::header_key(&entry.header).is_some() {
+            entry.header.nlink = 1;
+        }
+        entry.data = EntryData::Reader(Box::new(data));
+        true
+    }
+
+    /// Add object with `header` and `data` after all objects
+    pub fn insert(&mut self, header: Header, data: impl ReadSeek + 'static) {
+        self.entries.push(Entry { header, data: EntryData::Reader(Box::new(data)) });
+    }
+
+    /// Push all objects to `writer`, in order
+    ///
+    /// The format of `writer` can differ from the source archive. Call [`ArchiveWriter::write`]
+    /// afterwards to write the archive.
+    pub fn push_to<C: CpioHeader + Debug>(
+        self,
+        writer: &mut ArchiveWriter<'_, C>,
+    ) -> Result<(), CpioError> {
+        for entry in self.entries {
+            match entry.data {
+                EntryData::Source { offset, filesize } => {
+                    let source =
+                        SourceData { source: self.source.clone(), offset, filesize, pos: 0 };
+                    writer.push_file(source, entry.header)?;
+                }
+                EntryData::Reader(reader) => writer.push_file(reader, entry.header)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Index of the last entry at `path`
+    fn find(&self, path: &[u8]) -> Option<usize> {
+        let path = normalize_path(path);
+        self.entries.iter().rposition(|entry| normalize_path(&entry.header.name) == path)
+    }
+}
+
+/// Data of an object in the source archive, sharing the reader with the other objects
+struct SourceData {
+    source: Rc<RefCell<Box<dyn ReadSeek>>>,
+    offset: u64,
+    filesize: u64,
+    pos: u64,
+}
+
+impl Read for SourceData {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.filesize.saturating_sub(self.pos).min(buf.len() as u64) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        let mut source = self.source.borrow_mut();
+        source.seek(SeekFrom::Start(self.offset + self.pos))?;
+        let n = source.read(&mut buf[..len])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SourceData {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.filesize.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        self.pos = pos.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        Ok(self.pos)
+    }
+}
@@ -1,5 +1,14 @@
+use deku::DekuError;
+use no_std_io2::io::ErrorKind;
 use thiserror::Error;
 
+use crate::{Compression, FileType, Format};
+
+/// Parse error messages from header readers, converted to [`CpioError`] variants
+pub(crate) const INVALID_HEX: &str = "invalid hex digit";
+pub(crate) const INVALID_OCTAL: &str = "invalid octal digit";
+pub(crate) const MISSING_NUL: &str = "name missing NUL terminator";
+
 /// Errors generated from library
 #[derive(Error, Debug)]
 pub enum CpioError {
@@ -8,4 +17,65 @@ pub enum CpioError {
 
     #[error("deku error: {0:?}")]
     Deku(#[from] deku::DekuError),
+
+    #[error("checksum mismatch: expected {expected:#010x}, found {found:#010x}")]
+    ChecksumMismatch { expected: u32, found: u32 },
+
+    #[error("unknown format, magic doesn't match any supported cpio format")]
+    UnknownFormat,
+
+    #[error("invalid magic in header at offset {offset:#x}")]
+    InvalidMagic { offset: u64 },
+
+    #[error("invalid hex digit in header at offset {offset:#x}")]
+    InvalidHex { offset: u64 },
+
+    #[error("invalid octal digit in header at offset {offset:#x}")]
+    InvalidOctal { offset: u64 },
+
+    #[error("name missing NUL terminator in header at offset {offset:#x}")]
+    MissingNul { offset: u64 },
+
+    #[error("truncated header or data at offset {offset:#x}")]
+    Truncated { offset: u64 },
+
+    #[error("{0:?} compression not supported, enable the feature of the compression")]
+    UnsupportedCompression(Compression),
+
+    #[error("data after the trailer of the archive at offset {offset:#x}")]
+    TrailingData { offset: u64 },
+
+    #[error("name contains a NUL byte")]
+    InvalidName,
+
+    #[error("{0:?} can't contain data")]
+    UnexpectedData(FileType),
+
+    #[error("{0:?} requires data")]
+    MissingData(FileType),
+
+    #[error("device number {major}:{minor} can't be stored in header")]
+    DeviceOutOfRange { major: u32, minor: u32 },
+
+    #[error("{field} of {value} doesn't fit in {format:?} header")]
+    FieldOverflow { format: Format, field: &'static str, value: u64 },
+
+    #[error("invalid file list at line {line}: {reason}")]
+    InvalidList { line: usize, reason: &'static str },
+}
+
+impl CpioError {
+    /// Convert error from reading the object with header at `offset`
+    pub(crate) fn from_deku(e: DekuError, offset: u64) -> Self {
+        match e {
+            DekuError::Incomplete(_) | DekuError::Io(ErrorKind::UnexpectedEof) => {
+                Self::Truncated { offset }
+            }
+            DekuError::Assertion(_) => Self::InvalidMagic { offset },
+            DekuError::Parse(msg) if msg == INVALID_HEX => Self::InvalidHex { offset },
+            DekuError::Parse(msg) if msg == INVALID_OCTAL => Self::InvalidOctal { offset },
+            DekuError::Parse(msg) if msg == MISSING_NUL => Self::MissingNul { offset },
+            e => Self::Deku(e),
+        }
+    }
 }
@@ -0,0 +1,373 @@
+extern crate std;
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ffi::{CString, OsStr};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt, lchown};
+use std::path::{Path, PathBuf};
+use std::vec::Vec;
+
+use crate::{
+    CpioError, CpioHeader, CpioReader, FileType, Object, ReadSeek, TRAILER, normalize_path,
+};
+use crate::{cpio_header, link};
+
+/// Extract objects to the filesystem, creating each object according to the file type of `mode`
+///
+/// | Type | Created as |
+/// |------|------------|
+/// | regular file | file containing the data |
+/// | directory | directory |
+/// | symlink | symlink to the data |
+/// | FIFO, block and character device, socket | node with `rdev`, see [`Self::special_files`] |
+///
+/// Hard links, see [`LinkGroup`](crate::LinkGroup), are created as links to the first extracted
+/// link of the file, or as a new file if that link was replaced by a later object. The data is
+/// written when a link contains data, so links with the data on the last link as in newc archives
+/// are extracted with the data.
+///
+/// Existing files are replaced, and existing directories are kept. Permissions, ownership and
+/// modification times of directories are applied after all objects are extracted, so their
+/// contents can be created first.
+///
+/// By default, objects are extracted in safe mode for untrusted archives, see [`Self::safe`].
+///
+/// # Example
+/// Extract all objects of `archive.cpio` to `out`.
+/// ```rust, no_run
+/// # use librarium::{ArchiveReader, Extractor, NewcHeader};
+/// # use std::fs::File;
+/// let file = File::open("archive.cpio").unwrap();
+/// let mut archive = ArchiveReader::<NewcHeader>::from_reader(file).unwrap();
+/// Extractor::new("out")
+///     .special_files(false)
+///     .extract(&mut archive.reader, &archive.objects.inner)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Extractor {
+    dest: PathBuf,
+    permissions: bool,
+    ownership: bool,
+    mtimes: bool,
+    special_files: bool,
+    skip_unpermitted: bool,
+    safe: bool,
+}
+
+/// Object not extracted by [`Extractor`] in safe mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejected {
+    /// Name of object in archive
+    pub name: Vec<u8>,
+    pub reason: RejectReason,
+}
+
+/// Reason an object was [`Rejected`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    /// Name contains a `..` component
+    ParentDir,
+    /// Path to the object contains a symlink
+    Symlink,
+}
+
+impl Extractor {
+    /// Create new `Extractor` to `dest`, applying permissions and modification times, and
+    /// ownership only if running as root
+    pub fn new(dest: impl Into<PathBuf>) -> Self {
+        // SAFETY: geteuid is always successful
+        let root = unsafe { libc::geteuid() } == 0;
+        Self {
+            dest: dest.into(),
+            permissions: true,
+            ownership: root,
+            mtimes: true,
+            special_files: true,
+            skip_unpermitted: false,
+            safe: true,
+        }
+    }
+
+    /// Apply the permission bits of `mode`
+    pub fn permissions(mut self, permissions: bool) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Apply `uid` and `gid`, usually requires running as root
+    pub fn ownership(mut self, ownership: bool) -> Self {
+        self.ownership = ownership;
+        self
+    }
+
+    /// Apply `mtime`
+    pub fn mtimes(mut self, mtimes: bool) -> Self {
+        self.mtimes = mtimes;
+        self
+    }
+
+    /// Create FIFOs, devices and sockets, creating devices usually requires running as root
+    pub fn special_files(mut self, special_files: bool) -> Self {
+        self.special_files = special_files;
+        self
+    }
+
+    /// Skip creating devices and applying ownership when not permitted, instead of returning an
+    /// error
+    pub fn skip_unpermitted(mut self, skip_unpermitted: bool) -> Self {
+        self.skip_unpermitted = skip_unpermitted;
+        self
+    }
+
+    /// Only extract objects inside of the destination, default `true`
+    ///
+    /// Names are normalized with [`normalize_path`], removing leading `/`. Objects with `..`
+    /// components in the name, or with a symlink in the path to the object such as a symlink
+    /// created by an earlier object, are not extracted and returned as [`Rejected`].
+    pub fn safe(mut self, safe: bool) -> Self {
+        self.safe = safe;
+        self
+    }
+
+    /// Extract `objects` with data read from `reader`, skipping the Trailer entry
+    ///
+    /// Returns the objects rejected in safe mode.
+    pub fn extract<'a, R, C>(
+        &self,
+        reader: &mut R,
+        objects: impl IntoIterator<Item = &'a Object<C>>,
+    ) -> Result<Vec<Rejected>, CpioError>
+    where
+        R: ReadSeek,
+        C: CpioHeader + 'a,
+    {
+        fs::create_dir_all(&self.dest)?;
+
+        // applied after contents of directories
+        let mut dirs = Vec::new();
+        let mut rejected = Vec::new();
+        // normalized name of first extracted link to each file
+        let mut links: BTreeMap<_, Vec<u8>> = BTreeMap::new();
+        // file of each name in `links`
+        let mut link_keys = BTreeMap::new();
+        for object in objects {
+            let header = &object.header;
+            let name = normalize_path(header.name_bytes());
+            if header.name_bytes() == TRAILER.as_bytes() || name.is_empty() {
+                continue;
+            }
+            if self.safe {
+                if let Some(reason) = self.check(&name)? {
+                    log::warn!("rejected {:?}: {reason:?}", header.name_lossy());
+                    rejected.push(Rejected { name: header.name_bytes().to_vec(), reason });
+                    continue;
+                }
+            }
+            let path = self.dest.join(OsStr::from_bytes(&name));
+            log::info!("extracting: {:?} -> {path:?}", header.name_lossy());
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mode = header.mode();
+            if header.file_type().is_some_and(|t| t.is_special()) && !self.special_files {
+                log::debug!("skipping special file: {:?}", header.name_lossy());
+                continue;
+            }
+            // a later link to a replaced file can't be created as a link to the replacement
+            if let Some(key) = link_keys.remove(&name) {
+                links.remove(&key);
+            }
+            match header.file_type() {
+                Some(FileType::Directory) => {
+                    if !fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir()) {
+                        remove(&path)?;
+                        fs::create_dir(&path)?;
+                    }
+                    dirs.push((path, header));
+                    continue;
+                }
+                Some(FileType::Symlink) => {
+                    let mut target = Vec::new();
+                    reader.extract_data(object, &mut target)?;
+                    remove(&path)?;
+                    std::os::unix::fs::symlink(OsStr::from_bytes(&target), &path)?;
+                }
+                Some(file_type) if file_type.is_special() => {
+                    remove(&path)?;
+                    let (major, minor) = cpio_header::rdev_numbers(header);
+                    let rdev = libc::makedev(major as _, minor as _);
+                    match mknod(&path, mode, rdev) {
+                        Err(e) if self.skip_unpermitted && is_unpermitted(&e) => {
+                            log::warn!("skipping {:?}: {e}", header.name_lossy());
+                            continue;
+                        }
+                        result => result?,
+                    }
+                }
+                Some(FileType::Regular) => {
+                    let key = link::key(header);
+                    let target = key.and_then(|key| links.get(&key));
+                    if let Some(target) = target {
+                        // a symlink could have replaced a directory of the target since
+                        if self.safe {
+                            if let Some(reason) = self.check(target)? {
+                                log::warn!("rejected {:?}: {reason:?}", header.name_lossy());
+                                let name = header.name_bytes().to_vec();
+                                rejected.push(Rejected { name, reason });
+                                continue;
+                            }
+                        }
+                        let target = self.dest.join(OsStr::from_bytes(target));
+                        if !fs::symlink_metadata(&target).is_ok_and(|metadata| metadata.is_file()) {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                std::format!("hard link target isn't a regular file: {target:?}"),
+                            )
+                            .into());
+                        }
+                        remove(&path)?;
+                        fs::hard_link(&target, &path)?;
+                        if header.filesize() != 0 {
+                            // permissions of the first link were already applied
+                            if self.permissions {
+                                fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+                            }
+                            let mut file = OpenOptions::new()
+                                .write(true)
+                                .truncate(true)
+                                .custom_flags(libc::O_NOFOLLOW)
+                                .open(&path)?;
+                            reader.extract_data(object, &mut file)?;
+                            file.flush()?;
+                        }
+                    } else {
+                        remove(&path)?;
+                        let mut file = OpenOptions::new()
+                            .write(true)
+                            .create_new(true)
+                            .custom_flags(libc::O_NOFOLLOW)
+                            .open(&path)?;
+                        reader.extract_data(object, &mut file)?;
+                        file.flush()?;
+                        if let Some(key) = key {
+                            links.insert(key, name.clone());
+                            link_keys.insert(name.clone(), key);
+                        }
+                    }
+                }
+                _ => {
+                    log::warn!("skipping {:?}: unknown file type {mode:#o}", header.name_lossy());
+                    continue;
+                }
+            }
+            self.apply(&path, header)?;
+        }
+
+        // deepest directories first, so parents mtimes aren't changed by applying their children
+        // and children can still be reached after the permissions of their parents are applied
+        dirs.sort_by_key(|(path, _)| Reverse(path.components().count()));
+        for (path, header) in dirs {
+            self.apply(&path, header)?;
+        }
+
+        Ok(rejected)
+    }
+
+    /// Check that normalized `name` is inside of the destination, without following symlinks
+    fn check(&self, name: &[u8]) -> Result<Option<RejectReason>, CpioError> {
+        let components: Vec<_> = name.split(|b| *b == b'/').collect();
+        if components.contains(&&b".."[..]) {
+            return Ok(Some(RejectReason::ParentDir));
+        }
+
+        let mut path = self.dest.clone();
+        for component in &components[..components.len() - 1] {
+            path.push(OsStr::from_bytes(component));
+            match fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Ok(Some(RejectReason::Symlink));
+                }
+                Ok(_) => (),
+                // created when extracting
+                Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Apply ownership, permissions and mtime of `header` to `path`
+    fn apply<C: CpioHeader>(&self, path: &Path, header: &C) -> Result<(), CpioError> {
+        let is_symlink = header.file_type() == Some(FileType::Symlink);
+
+        // before permissions, as chown clears setuid and setgid bits
+        if self.ownership {
+            match lchown(path, Some(header.uid()), Some(header.gid())) {
+                Err(e) if self.skip_unpermitted && is_unpermitted(&e) => {
+                    log::warn!("skipping ownership of {path:?}: {e}");
+                }
+                result => result?,
+            }
+        }
+        if self.permissions && !is_symlink {
+            fs::set_permissions(path, fs::Permissions::from_mode(header.permissions().bits()))?;
+        }
+        if self.mtimes {
+            set_mtime(path, header.mtime())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Remove file at `path` if it exists and isn't a directory
+fn remove(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            std::format!("directory exists: {path:?}"),
+        )),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn is_unpermitted(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::PermissionDenied
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(io::Error::from)
+}
+
+fn mknod(path: &Path, mode: u32, rdev: libc::dev_t) -> io::Result<()> {
+    let path = c_path(path)?;
+    // SAFETY: path is a valid NUL terminated string
+    if unsafe { libc::mknod(path.as_ptr(), mode as libc::mode_t, rdev) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Set access and modification time of `path` to `mtime`, without following symlinks
+fn set_mtime(path: &Path, mtime: u64) -> io::Result<()> {
+    let path = c_path(path)?;
+    let tv_sec = libc::time_t::try_from(mtime).unwrap_or(libc::time_t::MAX);
+    let time = libc::timespec { tv_sec, tv_nsec: 0 };
+    let times = [time, time];
+    // SAFETY: path is a valid NUL terminated string, and times has two elements
+    let ret = unsafe {
+        libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW)
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use crate::{BinHeader, CpioError, CpioHeader, CrcHeader, Header, NewcHeader, OdcHeader, ReadSeek};
+use deku::ctx::Endian;
+use deku::prelude::*;
+use no_std_io2::io::{Read, Seek, SeekFrom, Write};
+
+/// Supported cpio formats
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// [`NewcHeader`]
+    Newc,
+    /// [`CrcHeader`]
+    Crc,
+    /// [`OdcHeader`]
+    Odc,
+    /// [`BinHeader`]
+    Bin,
+}
+
+impl Format {
+    /// Bytes needed by [`Self::from_magic`]
+    pub const MAGIC_LEN: usize = 6;
+
+    /// Detect format from the magic at the start of `bytes`
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [b'0', b'7', b'0', b'7', b'0', b'1', ..] => Some(Self::Newc),
+            [b'0', b'7', b'0', b'7', b'0', b'2', ..] => Some(Self::Crc),
+            [b'0', b'7', b'0', b'7', b'0', b'7', ..] => Some(Self::Odc),
+            [0xc7, 0x71, ..] | [0x71, 0xc7, ..] => Some(Self::Bin),
+            _ => None,
+        }
+    }
+
+    /// Detect format from the magic at the current position of `reader`, without advancing it
+    pub fn detect<R: ReadSeek + ?Sized>(reader: &mut R) -> Result<Self, CpioError> {
+        let (magic, len) = peek_magic(reader)?;
+        Self::from_magic(&magic[..len]).ok_or(CpioError::UnknownFormat)
+    }
+}
+
+/// Read up to [`Format::MAGIC_LEN`] bytes at the current position of `reader` without advancing
+/// it, returning the bytes and the number of bytes read
+pub(crate) fn peek_magic<R: ReadSeek + ?Sized>(
+    reader: &mut R,
+) -> Result<([u8; Format::MAGIC_LEN], usize), CpioError> {
+    let mut magic = [0; Format::MAGIC_LEN];
+    let mut len = 0;
+    while len < magic.len() {
+        let n = reader.read(&mut magic[len..])?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    reader.seek(SeekFrom::Current(-(len as i64)))?;
+
+    Ok((magic, len))
+}
+
+/// Any supported cpio header, with the format detected from the magic of each header
+///
+/// Creating a header with [`CpioHeader::from_header`] uses [`Format::Newc`], while
+/// [`CpioHeader::new_like`] keeps the format of the existing header.
+///
+/// # Example
+/// Read `archive.cpio` without knowing the format.
+/// ```rust, no_run
+/// # use librarium::{AnyHeader, ArchiveReader, CpioHeader};
+/// # use std::fs::File;
+/// let mut file = File::open("archive.cpio").unwrap();
+/// let archive = ArchiveReader::<AnyHeader>::detect(&mut file, 0).unwrap();
+/// println!("format: {:?}", archive.format());
+/// for object in &archive.objects.inner {
+///     println!("{}", object.header.name_lossy());
+/// }
+/// ```
+#[derive(Debug)]
+pub enum AnyHeader {
+    Newc(NewcHeader),
+    Crc(CrcHeader),
+    Odc(OdcHeader),
+    Bin(BinHeader),
+}
+
+impl AnyHeader {
+    /// Format of header
+    pub fn format(&self) -> Format {
+        match self {
+            Self::Newc(_) => Format::Newc,
+            Self::Crc(_) => Format::Crc,
+            Self::Odc(_) => Format::Odc,
+            Self::Bin(_) => Format::Bin,
+        }
+    }
+}
+
+/// Call `$e` with `$header` bound to the inner header of [`AnyHeader`]
+macro_rules! dispatch {
+    ($self:ident, $header:ident => $e:expr) => {
+        match $self {
+            AnyHeader::Newc($header) => $e,
+            AnyHeader::Crc($header) => $e,
+            AnyHeader::Odc($header) => $e,
+            AnyHeader::Bin($header) => $e,
+        }
+    };
+}
+
+impl<'a> DekuReader<'a> for AnyHeader {
+    fn from_reader_with_ctx<R: Read + Seek>(
+        reader: &mut Reader<R>,
+        _: (),
+    ) -> Result<Self, DekuError> {
+        let format = Format::detect(reader.as_mut()).map_err(|e| match e {
+            CpioError::StdIo(e) => DekuError::Io(e.kind()),
+            _ => deku::deku_error!(DekuError::Assertion, "unknown cpio magic"),
+        })?;
+        let header = match format {
+            Format::Newc => Self::Newc(NewcHeader::from_reader_with_ctx(reader, ())?),
+            Format::Crc => Self::Crc(CrcHeader::from_reader_with_ctx(reader, ())?),
+            Format::Odc => Self::Odc(OdcHeader::from_reader_with_ctx(reader, ())?),
+            Format::Bin => Self::Bin(BinHeader::from_reader_with_ctx(reader, ())?),
+        };
+
+        Ok(header)
+    }
+}
+
+impl DekuWriter for AnyHeader {
+    fn to_writer<W: Write + Seek>(&self, writer: &mut Writer<W>, _: ()) -> Result<(), DekuError> {
+        dispatch!(self, header => header.to_writer(writer, ()))
+    }
+}
+
+impl CpioHeader for AnyHeader {
+    fn from_header(header: Header, filesize: u64) -> Result<Self, CpioError> {
+        NewcHeader::from_header(header, filesize).map(Self::Newc)
+    }
+
+    fn new_like(&self, header: Header, filesize: u64) -> Result<Self, CpioError> {
+        match self {
+            Self::Newc(_) => NewcHeader::from_header(header, filesize).map(Self::Newc),
+            Self::Crc(_) => CrcHeader::from_header(header, filesize).map(Self::Crc),
+            Self::Odc(_) => OdcHeader::from_header(header, filesize).map(Self::Odc),
+            Self::Bin(_) => BinHeader::from_header(header, filesize).map(Self::Bin),
+        }
+    }
+
+    fn as_header(&self) -> Header {
+        dispatch!(self, header => header.as_header())
+    }
+
+    fn ino(&self) -> u32 {
+        dispatch!(self, header => header.ino())
+    }
+
+    fn mode(&self) -> u32 {
+        dispatch!(self, header => header.mode())
+    }
+
+    fn uid(&self) -> u32 {
+        dispatch!(self, header => header.uid())
+    }
+
+    fn gid(&self) -> u32 {
+        dispatch!(self, header => header.gid())
+    }
+
+    fn nlink(&self) -> u32 {
+        dispatch!(self, header => header.nlink())
+    }
+
+    fn mtime(&self) -> u64 {
+        dispatch!(self, header => header.mtime())
+    }
+
+    fn filesize(&self) -> u64 {
+        dispatch!(self, header => header.filesize())
+    }
+
+    fn set_ino(&mut self, ino: u32) -> Result<(), CpioError> {
+        dispatch!(self, header => header.set_ino(ino))
+    }
+
+    fn set_nlink(&mut self, nlink: u32) -> Result<(), CpioError> {
+        dispatch!(self, header => header.set_nlink(nlink))
+    }
+
+    fn set_filesize(&mut self, filesize: u64) -> Result<(), CpioError> {
+        dispatch!(self, header => header.set_filesize(filesize))
+    }
+
+    fn data_on_last_link(&self) -> bool {
+        dispatch!(self, header => header.data_on_last_link())
+    }
+
+    fn dev(&self) -> Option<u32> {
+        dispatch!(self, header => header.dev())
+    }
+
+    fn devmajor(&self) -> Option<u32> {
+        dispatch!(self, header => header.devmajor())
+    }
+
+    fn devminor(&self) -> Option<u32> {
+        dispatch!(self, header => header.devminor())
+    }
+
+    fn rdev(&self) -> Option<u32> {
+        dispatch!(self, header => header.rdev())
+    }
+
+    fn rdevmajor(&self) -> Option<u32> {
+        dispatch!(self, header => header.rdevmajor())
+    }
+
+    fn rdevminor(&self) -> Option<u32> {
+        dispatch!(self, header => header.rdevminor())
+    }
+
+    fn namesize(&self) -> u32 {
+        dispatch!(self, header => header.namesize())
+    }
+
+    fn check(&self) -> Option<u32> {
+        dispatch!(self, header => header.check())
+    }
+
+    fn has_checksum(&self) -> bool {
+        dispatch!(self, header => header.has_checksum())
+    }
+
+    fn set_check(&mut self, check: u32) {
+        dispatch!(self, header => header.set_check(check))
+    }
+
+    fn endian(&self) -> Endian {
+        dispatch!(self, header => header.endian())
+    }
+
+    fn set_endian(&mut self, endian: Endian) {
+        dispatch!(self, header => header.set_endian(endian))
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        dispatch!(self, header => header.name_bytes())
+    }
+
+    fn data_pad(&self) -> usize {
+        dispatch!(self, header => header.data_pad())
+    }
+}
@@ -0,0 +1,371 @@
+extern crate std;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::string::String;
+use std::time::SystemTime;
+use std::vec::Vec;
+
+use crate::tree::LazyFile;
+use crate::{
+    ArchiveReader, ArchiveWriter, CpioError, CpioHeader, FileType, Header, Permissions, TRAILER,
+    link, normalize_path,
+};
+
+/// Inode number of the first entry, as in `gen_init_cpio`
+const FIRST_INO: u32 = 721;
+
+/// File list of the Linux kernel's `usr/gen_init_cpio`
+///
+/// Each line describes one entry, with `mode` in octal and the other numbers in decimal. Empty
+/// lines and lines starting with `#` are ignored.
+///
+/// ```text
+/// file <name> <location> <mode> <uid> <gid> [<hard links>...]
+/// dir <name> <mode> <uid> <gid>
+/// nod <name> <mode> <uid> <gid> <b|c> <major> <minor>
+/// slink <name> <target> <mode> <uid> <gid>
+/// pipe <name> <mode> <uid> <gid>
+/// sock <name> <mode> <uid> <gid>
+/// ```
+///
+/// The list is written with [`fmt::Display`], so lists can be parsed, changed and written back.
+///
+/// # Example
+/// Create `initramfs.cpio` from `initramfs.list`.
+/// ```rust, no_run
+/// # use librarium::{ArchiveWriter, FileList, NewcHeader};
+/// # use std::fs::{self, File};
+/// let list = FileList::parse(&fs::read_to_string("initramfs.list").unwrap()).unwrap();
+/// let file = File::create("initramfs.cpio").unwrap();
+/// let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(file));
+/// list.push_to(&mut writer).unwrap();
+/// writer.write().unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileList {
+    pub entries: Vec<ListEntry>,
+    /// `mtime` of all entries when pushed, as with `gen_init_cpio -t`
+    ///
+    /// If `None`, regular files have the `mtime` of the file at their location and other entries
+    /// the current time, as without `-t`.
+    pub mtime: Option<u64>,
+}
+
+/// Line of a [`FileList`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListEntry {
+    /// Name in the archive, leading `/` are removed when pushed
+    pub name: String,
+    pub kind: ListKind,
+    pub permissions: Permissions,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// Type of a [`ListEntry`], with the fields of the type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListKind {
+    /// Regular file with the contents of the file at `location`, and hard links to it at `links`
+    ///
+    /// `${VAR}` in `location` is replaced by the environment variable `VAR` when pushed.
+    File {
+        location: String,
+        links: Vec<String>,
+    },
+    Dir,
+    /// Character or block device, with a `file_type` of [`FileType::CharDevice`] or
+    /// [`FileType::BlockDevice`]
+    Nod {
+        file_type: FileType,
+        major: u32,
+        minor: u32,
+    },
+    Slink {
+        target: String,
+    },
+    Pipe,
+    Sock,
+}
+
+impl FileList {
+    /// Parse file list from `list`
+    ///
+    /// Returns [`CpioError::InvalidList`] with the line number of the first invalid line. Fields
+    /// after the fields of the type are ignored, except for the hard links of `file`.
+    pub fn parse(list: &str) -> Result<Self, CpioError> {
+        let mut entries = Vec::new();
+        for (i, line) in list.lines().enumerate() {
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = parse_entry(line)
+                .map_err(|reason| CpioError::InvalidList { line: i + 1, reason })?;
+            entries.push(entry);
+        }
+
+        Ok(Self { entries, mtime: None })
+    }
+
+    /// List of the objects of `archive`, with the data of regular files at `root`
+    ///
+    /// The `location` of each regular file is the path the file is extracted to by
+    /// [`Extractor`](crate::Extractor) at `root`, so the archive can be created again after
+    /// extracting it. Hard links are listed as links of the first link. `mtime`, `ino` and `dev`
+    /// aren't part of the list, and objects of unknown type are skipped.
+    ///
+    /// Returns an [`io::ErrorKind::InvalidData`] error if a name or symlink target isn't UTF-8
+    /// or contains whitespace, which can't be written in the list.
+    pub fn from_archive<C: CpioHeader>(
+        archive: &mut ArchiveReader<'_, C>,
+        root: &str,
+    ) -> Result<Self, CpioError> {
+        let groups = archive.hard_links();
+        let mut entries = Vec::new();
+        for (i, object) in archive.objects.inner.iter().enumerate() {
+            let header = &object.header;
+            let path = normalize_path(header.name_bytes());
+            if header.name_bytes() == TRAILER.as_bytes() || path.is_empty() {
+                continue;
+            }
+            let path = list_str(&path)?;
+
+            let kind = match header.file_type() {
+                Some(FileType::Regular) => {
+                    let group = link::key(header)
+                        .and_then(|_| groups.iter().find(|group| group.links.contains(&i)));
+                    let mut links = Vec::new();
+                    if let Some(group) = group {
+                        if group.links[0] != i {
+                            continue;
+                        }
+                        for &link in &group.links[1..] {
+                            let name = archive.objects.inner[link].header.name_bytes();
+                            links.push(std::format!("/{}", list_str(&normalize_path(name))?));
+                        }
+                    }
+                    ListKind::File { location: std::format!("{root}/{path}"), links }
+                }
+                Some(FileType::Directory) => ListKind::Dir,
+                Some(file_type @ (FileType::CharDevice | FileType::BlockDevice)) => {
+                    let (major, minor) = header.device().unwrap_or_default();
+                    ListKind::Nod { file_type, major, minor }
+                }
+                Some(FileType::Symlink) => {
+                    let target = object.symlink_target(&mut archive.reader)?.unwrap_or_default();
+                    ListKind::Slink { target: list_str(&target)?.into() }
+                }
+                Some(FileType::Fifo) => ListKind::Pipe,
+                Some(FileType::Socket) => ListKind::Sock,
+                None => {
+                    log::warn!("skipping {path:?}: unknown file type {:#o}", header.mode());
+                    continue;
+                }
+            };
+            entries.push(ListEntry {
+                name: std::format!("/{path}"),
+                kind,
+                permissions: header.permissions(),
+                uid: header.uid(),
+                gid: header.gid(),
+            });
+        }
+
+        Ok(Self { entries, mtime: None })
+    }
+
+    /// Push each entry to `writer`, in order of the list
+    ///
+    /// As with `gen_init_cpio`, inodes are numbered from 721, directories have an `nlink` of 2
+    /// and `mtime` is set as described by [`Self::mtime`]. See [`ArchiveWriter::set_reproducible`]
+    /// to clamp `mtime` instead.
+    ///
+    /// Returns an [`io::ErrorKind::InvalidInput`] error if the location of a regular file isn't a
+    /// regular file, or a `nod` entry isn't a character or block device.
+    pub fn push_to<C: CpioHeader + fmt::Debug>(
+        &self,
+        writer: &mut ArchiveWriter<'_, C>,
+    ) -> Result<(), CpioError> {
+        let now = || {
+            let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH);
+            now.map_or(0, |now| now.as_secs())
+        };
+        let mtime = self.mtime.unwrap_or_else(now);
+        for (ino, entry) in (FIRST_INO..).zip(&self.entries) {
+            // file type is set when pushed
+            let header_of = |name: &str| Header {
+                ino,
+                mode: FileType::Regular.bits() | entry.permissions.bits(),
+                uid: entry.uid,
+                gid: entry.gid,
+                nlink: 1,
+                mtime,
+                name: name.trim_start_matches('/').into(),
+                ..Header::default()
+            };
+            let mut header = header_of(&entry.name);
+            log::trace!("pushing {:?}", entry.name);
+
+            match &entry.kind {
+                ListKind::File { location, links } => {
+                    let path = PathBuf::from(expand_env(location, |var| std::env::var(var).ok()));
+                    let metadata = fs::metadata(&path)?;
+                    if !metadata.is_file() {
+                        let msg = std::format!("not a regular file: {path:?}");
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into());
+                    }
+                    let nlink = links.len() as u32 + 1;
+                    let mtime = self.mtime.unwrap_or(metadata.mtime().max(0) as u64);
+                    header.nlink = nlink;
+                    header.mtime = mtime;
+                    writer.push_file(LazyFile::new(path, metadata.len()), header)?;
+                    // data is taken from the first link when written
+                    for link in links {
+                        writer.push_empty(Header { nlink, mtime, ..header_of(link) })?;
+                    }
+                }
+                ListKind::Dir => {
+                    header.nlink = 2;
+                    writer.push_dir(header)?;
+                }
+                ListKind::Nod { file_type: FileType::BlockDevice, major, minor } => {
+                    writer.push_block_device(header, *major, *minor)?;
+                }
+                ListKind::Nod { file_type: FileType::CharDevice, major, minor } => {
+                    writer.push_char_device(header, *major, *minor)?;
+                }
+                ListKind::Nod { file_type, .. } => {
+                    let msg = std::format!("{file_type:?} isn't a device: {:?}", entry.name);
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into());
+                }
+                ListKind::Slink { target } => writer.push_symlink(header, target)?,
+                ListKind::Pipe => writer.push_fifo(header)?,
+                ListKind::Sock => {
+                    header.set_file_type(FileType::Socket);
+                    writer.push_empty(header)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for FileList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let ListEntry { name, kind, permissions, uid, gid } = entry;
+            let mode = permissions.bits();
+            match kind {
+                ListKind::File { location, links } => {
+                    write!(f, "file {name} {location} {mode:04o} {uid} {gid}")?;
+                    for link in links {
+                        write!(f, " {link}")?;
+                    }
+                    writeln!(f)?;
+                }
+                ListKind::Dir => writeln!(f, "dir {name} {mode:04o} {uid} {gid}")?,
+                ListKind::Nod { file_type, major, minor } => {
+                    let dev_type = match file_type {
+                        FileType::BlockDevice => 'b',
+                        FileType::CharDevice => 'c',
+                        // not a valid list, as it can't be pushed either
+                        _ => '?',
+                    };
+                    writeln!(f, "nod {name} {mode:04o} {uid} {gid} {dev_type} {major} {minor}")?;
+                }
+                ListKind::Slink { target } => {
+                    writeln!(f, "slink {name} {target} {mode:04o} {uid} {gid}")?;
+                }
+                ListKind::Pipe => writeln!(f, "pipe {name} {mode:04o} {uid} {gid}")?,
+                ListKind::Sock => writeln!(f, "sock {name} {mode:04o} {uid} {gid}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse non-empty `line`, returning the reason if it is invalid
+fn parse_entry(line: &str) -> Result<ListEntry, &'static str> {
+    let mut fields = line.split_whitespace();
+    let mut next = || fields.next().ok_or("missing field");
+
+    let kind = next()?;
+    let name = next()?.into();
+    let location = match kind {
+        "file" | "slink" => Some(String::from(next()?)),
+        "dir" | "nod" | "pipe" | "sock" => None,
+        _ => return Err("unknown type"),
+    };
+    let mode = u32::from_str_radix(next()?, 8).map_err(|_| "invalid mode")?;
+    let uid = next()?.parse().map_err(|_| "invalid uid")?;
+    let gid = next()?.parse().map_err(|_| "invalid gid")?;
+
+    let kind = match (kind, location) {
+        ("file", Some(location)) => {
+            ListKind::File { location, links: fields.map(String::from).collect() }
+        }
+        ("slink", Some(target)) => ListKind::Slink { target },
+        ("dir", _) => ListKind::Dir,
+        ("nod", _) => {
+            let file_type = match next()? {
+                "b" => FileType::BlockDevice,
+                "c" => FileType::CharDevice,
+                _ => return Err("invalid device type"),
+            };
+            let major = next()?.parse().map_err(|_| "invalid major")?;
+            let minor = next()?.parse().map_err(|_| "invalid minor")?;
+            ListKind::Nod { file_type, major, minor }
+        }
+        ("pipe", _) => ListKind::Pipe,
+        _ => ListKind::Sock,
+    };
+
+    Ok(ListEntry { name, kind, permissions: Permissions::from_bits(mode), uid, gid })
+}
+
+/// `bytes` as a field of a list, which must be UTF-8 without whitespace
+fn list_str(bytes: &[u8]) -> Result<&str, CpioError> {
+    match core::str::from_utf8(bytes) {
+        Ok(s) if !s.is_empty() && !s.contains(char::is_whitespace) => Ok(s),
+        _ => {
+            let msg = std::format!("can't write {:?} in file list", String::from_utf8_lossy(bytes));
+            Err(io::Error::new(io::ErrorKind::InvalidData, msg).into())
+        }
+    }
+}
+
+/// Replace each `${VAR}` in `location` with `var("VAR")`, or nothing if it is `None`
+fn expand_env(location: &str, var: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = location;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&var(&rest[start + 2..start + 2 + len]).unwrap_or_default());
+        rest = &rest[start + 2 + len + 1..];
+    }
+    expanded.push_str(rest);
+
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_env() {
+        let var = |var: &str| (var == "SRC").then(|| String::from("/src"));
+        assert_eq!(expand_env("${SRC}/init", var), "/src/init");
+        assert_eq!(expand_env("${SRC}${UNSET}/${SRC", var), "/src/${SRC");
+        assert_eq!(expand_env("init", var), "init");
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use core::ops::Bound;
+
+use crate::{CpioHeader, Object, TRAILER};
+
+/// Normalized path of object to index in archive
+pub(crate) type PathIndex = BTreeMap<Vec<u8>, usize>;
+
+/// Normalize `path` for lookups in an archive
+///
+/// Leading `/` and `./`, empty and `.` components and trailing `/` are removed, so `./bin/sh`,
+/// `/bin/sh` and `bin//sh/` are all `bin/sh`. The root directory is empty. Paths are bytes, so
+/// names that aren't valid UTF-8 can be looked up.
+pub fn normalize_path(path: impl AsRef<[u8]>) -> Vec<u8> {
+    let path = path.as_ref();
+    let mut normalized = Vec::with_capacity(path.len());
+    for component in path.split(|b| *b == b'/').filter(|c| !c.is_empty() && *c != b".") {
+        if !normalized.is_empty() {
+            normalized.push(b'/');
+        }
+        normalized.extend_from_slice(component);
+    }
+
+    normalized
+}
+
+/// Index `objects` by normalized path, excluding the Trailer entry
+///
+/// Later objects with the same path replace earlier objects, as when extracting.
+pub(crate) fn build<C: CpioHeader>(objects: &[Object<C>]) -> PathIndex {
+    objects
+        .iter()
+        .enumerate()
+        .filter(|(_, object)| object.header.name_bytes() != TRAILER.as_bytes())
+        .map(|(i, object)| (normalize_path(object.header.name_bytes()), i))
+        .collect()
+}
+
+/// Find index of object at `path`, using `index` if available
+pub(crate) fn find<C: CpioHeader>(
+    objects: &[Object<C>],
+    index: Option<&PathIndex>,
+    path: &[u8],
+) -> Option<usize> {
+    let path = normalize_path(path);
+    match index {
+        Some(index) => index.get(&path).copied(),
+        None => objects.iter().rposition(|object| {
+            let name = object.header.name_bytes();
+            name != TRAILER.as_bytes() && normalize_path(name) == path
+        }),
+    }
+}
+
+/// Indexes of objects below directory `dir` in `index`, in order of path
+pub(crate) fn under<'a>(index: &'a PathIndex, dir: &[u8]) -> impl Iterator<Item = usize> + 'a {
+    let mut prefix = normalize_path(dir);
+    if !prefix.is_empty() {
+        prefix.push(b'/');
+    }
+    index
+        .range::<[u8], _>((Bound::Included(prefix.as_slice()), Bound::Unbounded))
+        .take_while(move |(path, _)| path.starts_with(&prefix))
+        .filter(|(path, _)| !path.is_empty())
+        .map(|(_, i)| *i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("bin/sh"), b"bin/sh");
+        assert_eq!(normalize_path("./bin/sh"), b"bin/sh");
+        assert_eq!(normalize_path("/bin/sh"), b"bin/sh");
+        assert_eq!(normalize_path("bin//./sh/"), b"bin/sh");
+        assert_eq!(normalize_path("."), b"");
+        assert_eq!(normalize_path("/"), b"");
+        assert_eq!(normalize_path("../a"), b"../a");
+        assert_eq!(normalize_path(b"./caf\xe9/"), b"caf\xe9");
+    }
+}
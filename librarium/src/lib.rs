@@ -15,7 +15,7 @@ for object in &archive.objects.inner {
     let mut out = OpenOptions::new()
         .write(true)
         .create(true)
-        .open(object.header.as_header().name)
+        .open(object.header.name_os_str())
         .unwrap();
     archive.reader.extract_data(object, &mut out).unwrap();
 }
@@ -32,7 +32,7 @@ let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(file));
 
 // A
 let a_data = "a\n".as_bytes();
-let a_header = Header { name: "a".to_string(), ..Header::default()};
+let a_header = Header { name: "a".into(), ..Header::default()};
 writer.push_file(Cursor::new(a_data), a_header).unwrap();
 
 // write to archive
@@ -47,24 +47,23 @@ writer.write().unwrap();
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 #[cfg(feature = "alloc")]
-use alloc::{
-    boxed::Box,
-    string::{String, ToString},
-    vec,
-    vec::Vec,
-};
+use alloc::{borrow::Cow, boxed::Box, collections::BTreeMap, string::String, vec, vec::Vec};
 
 #[cfg(doctest)]
 #[doc = include_str!("../../README.md")]
 type _ReadmeTest = ();
 
 use core::fmt::Debug;
+use core::marker::PhantomData;
 
-use no_std_io2::io::{Cursor, Read, Seek, SeekFrom, Write};
+use no_std_io2::io::{Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
 
 use deku::DekuError;
+use deku::ctx::Endian;
 use deku::prelude::*;
 use deku::writer::Writer;
 
@@ -76,14 +75,61 @@ pub use cpio_header::CpioHeader;
 pub mod error;
 pub use error::CpioError;
 
+pub mod mode;
+pub use mode::{FileType, Permissions};
+
 pub mod read_seek;
-pub use read_seek::ReadSeek;
 pub(crate) use read_seek::ReaderWithOffset;
+pub use read_seek::{EntryReader, ReadSeek};
+
+pub mod stream;
+pub use stream::{StreamEntry, StreamReader};
 
 pub mod newc;
-pub use newc::NewcHeader;
+pub use newc::{NewcHeader, Svr4Header};
+pub mod crc;
+pub use crc::CrcHeader;
 pub mod odc;
 pub use odc::OdcHeader;
+pub mod bin;
+pub use bin::BinHeader;
+
+pub mod format;
+pub use format::{AnyHeader, Format};
+
+pub mod compression;
+pub use compression::Compression;
+
+pub mod index;
+use index::PathIndex;
+pub use index::normalize_path;
+
+pub mod segment;
+pub use segment::{MultiArchiveReader, Segment};
+
+pub mod link;
+pub use link::LinkGroup;
+
+pub mod reproducible;
+pub use reproducible::Reproducible;
+
+pub mod edit;
+pub use edit::ArchiveEditor;
+
+#[cfg(all(feature = "std", unix))]
+pub mod tree;
+#[cfg(all(feature = "std", unix))]
+pub use tree::TreeBuilder;
+
+#[cfg(all(feature = "std", unix))]
+pub mod gen_init_cpio;
+#[cfg(all(feature = "std", unix))]
+pub use gen_init_cpio::{FileList, ListEntry, ListKind};
+
+#[cfg(all(feature = "std", unix))]
+pub mod extract;
+#[cfg(all(feature = "std", unix))]
+pub use extract::{Extractor, RejectReason, Rejected};
 
 /// DekuWriter, but can write to self
 trait MutWriter<Ctx = ()> {
@@ -94,26 +140,84 @@ trait MutWriter<Ctx = ()> {
     ) -> core::result::Result<(), DekuError>;
 }
 
+/// Length of buffer used when copying data
+const COPY_BUF_LEN: usize = 0x2000;
+
+/// Maximum length of a symlink target read by [`Object::symlink_target`], `PATH_MAX` of Linux
+pub const SYMLINK_MAX: u32 = 0x1000;
+
 impl<T: ReadSeek> CpioReader for T {}
 /// Extract data from cpio Archive
 pub trait CpioReader: ReadSeek {
+    /// Copy data of `object` to `writer`, in chunks of at most `COPY_BUF_LEN` bytes
+    ///
+    /// If the header contains a checksum, it's verified after all data has been written to
+    /// `writer`.
     fn extract_data<W, C: CpioHeader>(
         &mut self,
         object: &Object<C>,
         writer: &mut W,
     ) -> Result<(), CpioError>
     where
-        W: Write + Seek,
+        W: Write,
     {
-        // found the file, seek forward
-        if let Data::Offset(offset) = object.data {
-            self.seek(SeekFrom::Start(offset)).unwrap();
-            let mut buf = vec![0; object.header.filesize() as usize];
-            self.read_exact(&mut buf).unwrap();
-            writer.write_all(&buf)?;
-            Ok(())
-        } else {
-            panic!("no offset! TODO improve this");
+        let mut reader = self.entry_reader(object)?;
+        let mut buf = vec![0; COPY_BUF_LEN];
+        let mut sum = 0;
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            if object.header.has_checksum() {
+                sum = crc::checksum(sum, &buf[..n]);
+            }
+            writer.write_all(&buf[..n])?;
+        }
+        if reader.remaining() != 0 {
+            return Err(CpioError::StdIo(ErrorKind::UnexpectedEof.into()));
+        }
+
+        if object.header.has_checksum() {
+            let expected = object.header.check().unwrap_or(0);
+            if expected != sum {
+                return Err(CpioError::ChecksumMismatch { expected, found: sum });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `Read` limited to the data of `object`
+    ///
+    /// # Example
+    /// Stream data of all objects in `archive.cpio`, without reading all data into memory.
+    /// ```rust, no_run
+    /// # use std::io::Read;
+    /// # use librarium::{ArchiveReader, NewcHeader, CpioReader, CpioHeader};
+    /// # use std::fs::File;
+    /// let mut file = File::open("archive.cpio").unwrap();
+    /// let mut archive = ArchiveReader::<NewcHeader>::from_reader(&mut file).unwrap();
+    ///
+    /// for object in &archive.objects.inner {
+    ///     let mut reader = archive.reader.entry_reader(object).unwrap();
+    ///     let mut buf = [0; 0x1000];
+    ///     while reader.read(&mut buf).unwrap() != 0 {
+    ///         // hash or decompress buf
+    ///     }
+    /// }
+    /// ```
+    fn entry_reader<C: CpioHeader>(
+        &mut self,
+        object: &Object<C>,
+    ) -> Result<EntryReader<'_, Self>, CpioError> {
+        match object.data {
+            Data::Offset(offset) => {
+                self.seek(SeekFrom::Start(offset))?;
+                Ok(EntryReader::new(self, object.header.filesize()))
+            }
+            // object wasn't read from an archive
+            _ => Err(CpioError::StdIo(ErrorKind::InvalidInput.into())),
         }
     }
 }
@@ -129,39 +233,63 @@ pub enum Data {
     Empty,
 }
 
-impl DekuReader<'_, u32> for Data {
+impl DekuReader<'_, u64> for Data {
     fn from_reader_with_ctx<R: Read + Seek>(
         reader: &mut Reader<R>,
-        filesize: u32,
+        filesize: u64,
     ) -> Result<Data, DekuError> {
         let reader = reader.as_mut();
 
         // Save the current offset, this is where the file exists for reading later
         #[allow(clippy::seek_from_current)]
-        let current_pos = reader.seek(SeekFrom::Current(0)).unwrap();
+        let current_pos = reader.seek(SeekFrom::Current(0)).map_err(|e| DekuError::Io(e.kind()))?;
 
         // Seek past that file
-        let position = filesize as i64;
-        let _ = reader.seek(SeekFrom::Current(position));
+        let position =
+            i64::try_from(filesize).map_err(|_| DekuError::Io(ErrorKind::InvalidData))?;
+        reader.seek(SeekFrom::Current(position)).map_err(|e| DekuError::Io(e.kind()))?;
 
         Ok(Self::Offset(current_pos))
     }
 }
 
-impl MutWriter<u32> for Data {
+impl Data {
+    /// Checksum of `Reader`, leaving it at the start of data
+    fn checksum(&mut self) -> Result<u32, CpioError> {
+        let mut sum = 0;
+        if let Self::Reader(reader) = self {
+            let mut buf = [0; 1024];
+            loop {
+                let n = reader.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                sum = crc::checksum(sum, &buf[..n]);
+            }
+            reader.seek(SeekFrom::Start(0))?;
+        }
+
+        Ok(sum)
+    }
+}
+
+impl MutWriter<u64> for Data {
     fn to_mutwriter<W: Write + Seek>(
         &mut self,
         writer: &mut Writer<W>,
-        _: u32,
+        filesize: u64,
     ) -> Result<(), DekuError> {
         match self {
             Self::Reader(reader) => {
-                // read from reader
-                let mut data = vec![];
-                reader.read_to_end(&mut data).unwrap();
-
-                // write to deku
-                data.to_writer(writer, ())?;
+                // copy filesize bytes from reader in chunks
+                let mut buf = vec![0; COPY_BUF_LEN];
+                let mut remaining = filesize;
+                while remaining != 0 {
+                    let len = remaining.min(buf.len() as u64) as usize;
+                    reader.read_exact(&mut buf[..len]).map_err(|e| DekuError::Io(e.kind()))?;
+                    writer.write_bytes(&buf[..len])?;
+                    remaining -= len as u64;
+                }
             }
             Self::Empty => (),
             _ => {
@@ -174,30 +302,115 @@ impl MutWriter<u32> for Data {
 }
 
 /// All objects in archive
-#[derive(DekuRead)]
 pub struct Objects<C: CpioHeader> {
-    #[deku(until = "Self::is_last")]
     pub inner: Vec<Object<C>>,
 }
 
-impl<C: CpioHeader> MutWriter for Objects<C> {
-    /// Write all entries
-    fn to_mutwriter<W: Write + Seek>(
-        &mut self,
-        deku_writer: &mut Writer<W>,
-        _: (),
-    ) -> core::result::Result<(), DekuError> {
-        for i in &mut self.inner {
-            i.to_mutwriter(deku_writer, ())?;
+/// Lazy iterator over the objects of an archive, reading each header when requested
+///
+/// Objects are read until and including the Trailer entry. Iteration stops after the first
+/// error, which contains the offset of the header of the object that could not be read.
+///
+/// `reader` can be used between calls to `next`, such as to extract the data of an object.
+///
+/// # Example
+/// Find `kernel/x86/microcode` without reading the rest of `archive.cpio`.
+/// ```rust, no_run
+/// # use librarium::{Entries, NewcHeader, CpioReader, CpioHeader};
+/// # use std::fs::File;
+/// let file = File::open("archive.cpio").unwrap();
+/// let mut entries = Entries::<NewcHeader>::from_reader(file).unwrap();
+/// while let Some(object) = entries.next() {
+///     let object = object.unwrap();
+///     if object.header.name() == Some("kernel/x86/microcode") {
+///         let mut out = vec![];
+///         entries.reader.extract_data(&object, &mut out).unwrap();
+///         break;
+///     }
+/// }
+/// ```
+pub struct Entries<'b, C: CpioHeader> {
+    pub reader: Box<dyn ReadSeek + 'b>,
+    /// Offset of next header
+    offset: u64,
+    /// Trailer entry or an error was read
+    finished: bool,
+    /// Length of `reader`
+    end: u64,
+    phantom: PhantomData<C>,
+}
+
+impl<'b, C: CpioHeader> Entries<'b, C> {
+    pub fn from_reader(reader: impl ReadSeek + 'b) -> Result<Self, CpioError> {
+        Self::from_reader_with_offset(reader, 0)
+    }
+
+    pub fn from_reader_with_offset(
+        reader: impl ReadSeek + 'b,
+        offset: u64,
+    ) -> Result<Self, CpioError> {
+        let mut reader: Box<dyn ReadSeek> = if offset == 0 {
+            Box::new(reader)
+        } else {
+            let reader = ReaderWithOffset::new(reader, offset)?;
+            Box::new(reader)
+        };
+        let end = reader.seek(SeekFrom::End(0))?;
+
+        Ok(Self::starting_at(reader, 0, end))
+    }
+
+    /// Read objects starting at `offset` of `reader` with length `end`
+    pub(crate) fn starting_at(reader: Box<dyn ReadSeek + 'b>, offset: u64, end: u64) -> Self {
+        Self { reader, offset, finished: false, end, phantom: PhantomData }
+    }
+
+    /// Offset of the next header, or after the Trailer entry once finished
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Read object at `offset`, returning the object and the offset of the next header
+    fn read_object(&mut self, offset: u64) -> Result<(Object<C>, u64), CpioError> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut deku_reader = Reader::new(&mut self.reader);
+        let object = Object::<C>::from_reader_with_ctx(&mut deku_reader, ())
+            .map_err(|e| CpioError::from_deku(e, offset))?;
+        if let Data::Offset(data_offset) = object.data {
+            if data_offset.checked_add(object.header.filesize()).is_none_or(|end| end > self.end) {
+                return Err(CpioError::Truncated { offset });
+            }
         }
-        Ok(())
+
+        #[allow(clippy::seek_from_current)]
+        let next_offset = self.reader.seek(SeekFrom::Current(0))?;
+        Ok((object, next_offset))
     }
-}
 
-impl<C: CpioHeader> Objects<C> {
     /// Is Trailer entry
     fn is_last(last_object: &Object<C>) -> bool {
-        last_object.header.name().as_bytes() == TRAILER.as_bytes()
+        last_object.header.name_bytes() == TRAILER.as_bytes()
+    }
+}
+
+impl<C: CpioHeader> Iterator for Entries<'_, C> {
+    type Item = Result<Object<C>, CpioError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_object(self.offset) {
+            Ok((object, next_offset)) => {
+                self.offset = next_offset;
+                self.finished = Self::is_last(&object);
+                Some(Ok(object))
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
     }
 }
 
@@ -218,7 +431,7 @@ impl<C: CpioHeader> Objects<C> {
 ///    let mut out = OpenOptions::new()
 ///        .write(true)
 ///        .create(true)
-///        .open(object.header.as_header().name)
+///        .open(object.header.name_os_str())
 ///        .unwrap();
 ///     archive.reader.extract_data(object, &mut out).unwrap();
 /// }
@@ -226,6 +439,8 @@ impl<C: CpioHeader> Objects<C> {
 pub struct ArchiveReader<'b, C: CpioHeader> {
     pub reader: Box<dyn ReadSeek + 'b>,
     pub objects: Objects<C>,
+    /// Built by [`Self::build_index`]
+    index: Option<PathIndex>,
 }
 
 impl<'b, C: CpioHeader> ArchiveReader<'b, C> {
@@ -237,32 +452,133 @@ impl<'b, C: CpioHeader> ArchiveReader<'b, C> {
         reader: impl ReadSeek + 'b,
         offset: u64,
     ) -> Result<Self, CpioError> {
-        let mut reader: Box<dyn ReadSeek> = if offset == 0 {
-            Box::new(reader)
-        } else {
-            let reader = ReaderWithOffset::new(reader, offset)?;
-            Box::new(reader)
+        let mut entries = Entries::from_reader_with_offset(reader, offset)?;
+        let inner = entries.by_ref().collect::<Result<_, _>>()?;
+        Ok(Self { reader: entries.reader, objects: Objects { inner }, index: None })
+    }
+
+    /// Index objects by path, making [`Self::get`] and other lookups by path O(log n)
+    ///
+    /// Without the index, lookups scan all objects. The index must be rebuilt after changing
+    /// `objects`.
+    pub fn build_index(&mut self) {
+        self.index = Some(index::build(&self.objects.inner));
+    }
+
+    /// Object at `path`, compared after [`normalize_path`]
+    ///
+    /// If more than one object has the same path, the last is returned, as when extracting.
+    pub fn get(&self, path: impl AsRef<[u8]>) -> Option<&Object<C>> {
+        index::find(&self.objects.inner, self.index.as_ref(), path.as_ref())
+            .map(|i| &self.objects.inner[i])
+    }
+
+    /// Archive contains an object at `path`
+    pub fn contains(&self, path: impl AsRef<[u8]>) -> bool {
+        self.get(path).is_some()
+    }
+
+    /// Header of object at `path`
+    pub fn metadata(&self, path: impl AsRef<[u8]>) -> Option<Header> {
+        self.get(path).map(|object| object.header.as_header())
+    }
+
+    /// All objects below directory `dir`, in order of path
+    ///
+    /// Builds a temporary index if [`Self::build_index`] wasn't called.
+    pub fn entries_under(&self, dir: impl AsRef<[u8]>) -> Vec<&Object<C>> {
+        let built;
+        let index = match &self.index {
+            Some(index) => index,
+            None => {
+                built = index::build(&self.objects.inner);
+                &built
+            }
         };
-        let (_, objects) = Objects::from_reader((&mut reader, 0))?;
-        Ok(Self { reader, objects })
+        index::under(index, dir.as_ref()).map(|i| &self.objects.inner[i]).collect()
     }
 
+    /// Extract data of object at `path`, see [`Self::get`]
+    ///
+    /// The data of a hard link is extracted from the link containing the data, see
+    /// [`Self::data_object`].
     pub fn extract_by_name<W>(
         &mut self,
-        name: &str,
+        name: impl AsRef<[u8]>,
         writer: &mut W,
     ) -> Result<Option<Header>, CpioError>
     where
-        W: Write + Seek,
+        W: Write,
     {
-        for object in &self.objects.inner {
-            if name == object.header.name() {
-                self.reader.extract_data(object, writer)?;
-                return Ok(Some(object.header.as_header()));
-            }
+        let Some(i) = index::find(&self.objects.inner, self.index.as_ref(), name.as_ref()) else {
+            return Ok(None);
+        };
+        let object = &self.objects.inner[i];
+        self.reader.extract_data(link::resolve(&self.objects.inner, object), writer)?;
+
+        Ok(Some(object.header.as_header()))
+    }
+
+    /// Target of symlink at `path`, see [`Object::symlink_target`]
+    pub fn symlink_target(&mut self, path: impl AsRef<[u8]>) -> Result<Option<Vec<u8>>, CpioError> {
+        match index::find(&self.objects.inner, self.index.as_ref(), path.as_ref()) {
+            Some(i) => self.objects.inner[i].symlink_target(&mut self.reader),
+            None => Ok(None),
         }
+    }
+
+    /// Groups of objects that are hard links to the same file
+    ///
+    /// Links are regular files with an `nlink` of more than 1 and the same `dev` and `ino`.
+    pub fn hard_links(&self) -> Vec<LinkGroup> {
+        link::link_groups(&self.objects.inner)
+    }
 
-        Ok(None)
+    /// Object containing the data of `object`
+    ///
+    /// This is `object`, unless it is an empty hard link and a later link to the same file
+    /// contains the data, as in newc archives.
+    pub fn data_object<'o>(&'o self, object: &'o Object<C>) -> &'o Object<C> {
+        link::resolve(&self.objects.inner, object)
+    }
+}
+
+impl<'b> ArchiveReader<'b, AnyHeader> {
+    /// Read archive of any supported [`Format`], detected from the magic at `offset`
+    ///
+    /// Compressed data at `offset` is decompressed first, see [`Compression`] for the required
+    /// features. The objects are read in one pass of the decompressed data, but extracting data
+    /// before the last position read decompresses again from the start, so extract in archive
+    /// order.
+    ///
+    /// Returns [`CpioError::UnknownFormat`] if the magic doesn't match any supported format or
+    /// compression.
+    pub fn detect(reader: impl ReadSeek + 'b, offset: u64) -> Result<Self, CpioError> {
+        let mut reader: Box<dyn ReadSeek + 'b> = Box::new(reader);
+        reader.seek(SeekFrom::Start(offset))?;
+        let compression = Compression::detect(&mut reader)?;
+        if let Some(compression) = compression {
+            reader = compression::decompress_at(reader, offset, compression)?;
+            reader.seek(SeekFrom::Start(offset))?;
+        }
+        let format = Format::detect(&mut reader)?;
+        log::debug!("detected format: {format:?}");
+
+        let mut reader = ReaderWithOffset::new(reader, offset)?;
+        // the length of decompressed data is only known after decompressing all of it, so
+        // truncated data is found when reading past the end instead
+        let end = match compression {
+            Some(_) => u64::MAX,
+            None => reader.seek(SeekFrom::End(0))?,
+        };
+        let mut entries = Entries::starting_at(Box::new(reader), 0, end);
+        let inner = entries.by_ref().collect::<Result<_, _>>()?;
+        Ok(Self { reader: entries.reader, objects: Objects { inner }, index: None })
+    }
+
+    /// Format of the first object in archive
+    pub fn format(&self) -> Option<Format> {
+        self.objects.inner.first().map(|object| object.header.format())
     }
 }
 
@@ -270,8 +586,23 @@ impl<'b, C: CpioHeader> ArchiveReader<'b, C> {
 pub trait WriteSeek: Write + Seek {}
 impl<T: Write + Seek> WriteSeek for T {}
 
+/// `Read` + `Write` + `Seek`
+pub trait ReadWriteSeek: Read + Write + Seek {}
+impl<T: Read + Write + Seek> ReadWriteSeek for T {}
+
 /// Write cpio Archive and add data
 ///
+/// By default, objects are kept until [`Self::write`]. Use [`Self::new_streaming`] to write
+/// each object when it is pushed, and [`Self::new_append`] to add objects to an existing archive.
+///
+/// Regular files pushed with an `nlink` of more than 1 and the same `dev` and `ino` are written
+/// as hard links to the same file by [`Self::write`], with `nlink` set to the number of links.
+/// In newc and crc archives only the last link contains the data, taken from the last link
+/// pushed with data, and in odc and bin archives every link contains the data. When streaming,
+/// links are written as pushed.
+///
+/// Use [`Self::set_reproducible`] to write the same archive from the same files.
+///
 /// # Example
 /// Create new cpio archive of Newc format and one file.
 ///
@@ -285,33 +616,227 @@ impl<T: Write + Seek> WriteSeek for T {}
 ///
 /// // A
 /// let a_data = "a\n".as_bytes();
-/// let a_header = Header { name: "a".to_string(), ..Header::default()};
+/// let a_header = Header { name: "a".into(), ..Header::default()};
 /// writer.push_file(Cursor::new(a_data), a_header).unwrap();
 ///
 /// // write to archive
 /// writer.write().unwrap();
 /// ```
 pub struct ArchiveWriter<'a, C: CpioHeader> {
-    writer: Box<dyn WriteSeek + 'a>,
+    writer: Output<'a>,
     objects: Objects<C>,
     pad_len: u32,
+    endian: Endian,
+    /// Write objects when pushed
+    streaming: bool,
+    /// Bytes written to `writer`, including the existing objects when appending
+    bytes_written: u64,
+    /// Length of the existing archive when appending, overwritten with padding if longer
+    append_end: u64,
+    reproducible: Option<Reproducible>,
+    inodes: reproducible::Inodes,
+    /// An object was pushed, so [`Self::set_reproducible`] can't apply to all objects
+    pushed: bool,
+    /// First header of the existing archive when appending, new headers are created in its format
+    existing: Option<C>,
 }
 
 impl<'a, C: CpioHeader + Debug> ArchiveWriter<'a, C> {
     /// Default image padding length
     pub const DEFAULT_PAD_LEN: u32 = 0x400;
 
-    /// Create new `ArchiveWriter` with no objects and image padding length of
-    /// `Self::DEFAULT_PAD_LEN`.
+    /// Create new `ArchiveWriter` with no objects, image padding length of
+    /// `Self::DEFAULT_PAD_LEN` and little endian binary headers.
     pub fn new(writer: Box<dyn WriteSeek + 'a>) -> Self {
-        Self { writer, objects: Objects { inner: vec![] }, pad_len: Self::DEFAULT_PAD_LEN }
+        Self::with_output(Output::Plain(writer))
+    }
+
+    fn with_output(writer: Output<'a>) -> Self {
+        Self {
+            writer,
+            objects: Objects { inner: vec![] },
+            pad_len: Self::DEFAULT_PAD_LEN,
+            endian: Endian::Little,
+            streaming: false,
+            bytes_written: 0,
+            append_end: 0,
+            reproducible: None,
+            inodes: reproducible::Inodes::default(),
+            pushed: false,
+            existing: None,
+        }
+    }
+
+    /// Create new `ArchiveWriter` that writes the header and data of each object when pushed,
+    /// instead of keeping all objects until written.
+    ///
+    /// Data is copied in chunks, and each reader is dropped after its data is written. Call
+    /// [`Self::finish`] to write the trailing `TRAILER!!!` entry and padding.
+    ///
+    /// # Example
+    /// ```rust, no_run
+    /// # use librarium::{Header, ArchiveWriter, NewcHeader};
+    /// # use std::fs::File;
+    /// let file = File::create("archive.cpio").unwrap();
+    /// let mut writer = ArchiveWriter::<NewcHeader>::new_streaming(Box::new(file));
+    ///
+    /// // data of "rootfs.img" is written here
+    /// let header = Header { name: "rootfs.img".into(), ..Header::default()};
+    /// writer.push_file(File::open("rootfs.img").unwrap(), header).unwrap();
+    ///
+    /// writer.finish().unwrap();
+    /// ```
+    pub fn new_streaming(writer: Box<dyn WriteSeek + 'a>) -> Self {
+        Self { streaming: true, ..Self::new(writer) }
+    }
+
+    /// Create new `ArchiveWriter` that adds objects to the existing archive in `file`, as with
+    /// `cpio -A`
+    ///
+    /// The objects of the archive are read to find the Trailer entry, which is overwritten by the
+    /// pushed objects. [`Self::write`] writes the trailer and pads the archive from the start of
+    /// `file`, overwriting the rest of `file` with zeros if the new archive is shorter. An empty
+    /// `file` is written as a new archive. Binary headers are written in the byte order of the
+    /// archive.
+    ///
+    /// The archive must be of format `C`, with [`AnyHeader`] new headers are created in the format
+    /// of the first existing header. Returns [`CpioError::Truncated`] if the archive
+    /// doesn't end with a Trailer entry, and [`CpioError::TrailingData`] if anything but NUL
+    /// padding follows it, such as the next archive of a multi-segment initramfs.
+    ///
+    /// # Example
+    /// Add `init` to `archive.cpio`.
+    /// ```rust, no_run
+    /// # use std::io::Cursor;
+    /// # use librarium::{ArchiveWriter, FileType, Header, NewcHeader, Permissions};
+    /// # use std::fs::OpenOptions;
+    /// let file = OpenOptions::new().read(true).write(true).open("archive.cpio").unwrap();
+    /// let mut writer = ArchiveWriter::<NewcHeader>::new_append(Box::new(file)).unwrap();
+    ///
+    /// let init = Header::new("init", FileType::Regular, Permissions::from_bits(0o755));
+    /// writer.push_file(Cursor::new(b"#!/bin/sh\n"), init).unwrap();
+    ///
+    /// writer.write().unwrap();
+    /// ```
+    pub fn new_append(mut file: Box<dyn ReadWriteSeek + 'a>) -> Result<Self, CpioError> {
+        let end = file.seek(SeekFrom::End(0))?;
+        let mut trailer = 0;
+        let mut endian = Endian::Little;
+        let mut existing = None;
+        if end != 0 {
+            let mut entries = Entries::<C>::from_reader(&mut file)?;
+            loop {
+                let offset = entries.offset();
+                let Some(object) = entries.next() else {
+                    return Err(CpioError::Truncated { offset });
+                };
+                let object = object?;
+                let last = Entries::is_last(&object);
+                if offset == 0 {
+                    endian = object.header.endian();
+                    existing = Some(object.header);
+                }
+                if last {
+                    trailer = offset;
+                    break;
+                }
+            }
+            let padding = entries.offset();
+            drop(entries);
+            if let Some(offset) = segment::skip_padding(&mut file, padding)? {
+                return Err(CpioError::TrailingData { offset });
+            }
+        }
+        file.seek(SeekFrom::Start(trailer))?;
+
+        let writer = Box::new(file) as Box<dyn WriteSeek + 'a>;
+        Ok(Self { bytes_written: trailer, append_end: end, endian, existing, ..Self::new(writer) })
+    }
+
+    /// Create new `ArchiveWriter` that compresses the archive written to `writer`, with
+    /// compression `level` or the default level of the compression if `None`
+    ///
+    /// xz is written with the CRC32 check and lz4 with the legacy frame format, as required by
+    /// the kernel for initramfs images. `level` isn't used by lz4.
+    ///
+    /// Returns [`CpioError::UnsupportedCompression`] if the feature of the compression isn't
+    /// enabled, and an `InvalidInput` error if `level` isn't a level of the compression: `0..=9`
+    /// for gzip, xz and lzma, `1..=9` for bzip2, and up to the maximum level of the zstd library.
+    ///
+    /// # Example
+    /// ```rust, no_run
+    /// # use std::io::Cursor;
+    /// # use librarium::{Header, ArchiveWriter, Compression, NewcHeader};
+    /// # use std::fs::File;
+    /// let file = File::create("initrd.img").unwrap();
+    /// let mut writer =
+    ///     ArchiveWriter::<NewcHeader>::new_compressed(Box::new(file), Compression::Zstd, Some(19))
+    ///         .unwrap();
+    ///
+    /// let header = Header { name: "init".into(), mode: 0o100755, ..Header::default()};
+    /// writer.push_file(Cursor::new(b"#!/bin/sh\n"), header).unwrap();
+    ///
+    /// writer.finish().unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn new_compressed(
+        writer: Box<dyn Write + 'a>,
+        compression: Compression,
+        level: Option<u32>,
+    ) -> Result<Self, CpioError> {
+        let writer = compression::CompressWriter::new(writer, compression, level)?;
+        Ok(Self::with_output(Output::Compressed(writer)))
     }
 
     pub fn set_pad_len(&mut self, pad_len: u32) {
         self.pad_len = pad_len;
     }
 
+    /// Set byte order of headers pushed after this call, only used by [`BinHeader`]
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    /// Write a reproducible archive, applied to the headers of all pushed objects
+    ///
+    /// Objects are sorted and renumbered by [`Self::write`]. When streaming, objects are written
+    /// in the order pushed and numbered as pushed.
+    ///
+    /// Returns an `InvalidInput` error if an object was already pushed, as its inode number
+    /// could be the same as a renumbered object.
+    pub fn set_reproducible(&mut self, reproducible: Reproducible) -> Result<(), CpioError> {
+        if self.pushed {
+            return Err(CpioError::StdIo(ErrorKind::InvalidInput.into()));
+        }
+        self.reproducible = Some(reproducible);
+        Ok(())
+    }
+
+    /// Convert `header` to the format, applying the byte order and reproducible options
+    fn format_header(&mut self, mut header: Header, filesize: u64) -> Result<C, CpioError> {
+        self.pushed = true;
+        if let Some(reproducible) = &self.reproducible {
+            let key = link::header_key(&header);
+            reproducible.apply(&mut header);
+            header.ino = self.inodes.assign(key);
+        }
+        self.new_header(header, filesize)
+    }
+
+    /// Create header in the format of the existing archive when appending, with the byte order
+    fn new_header(&self, header: Header, filesize: u64) -> Result<C, CpioError> {
+        let mut header = match &self.existing {
+            Some(existing) => existing.new_like(header, filesize)?,
+            None => C::from_header(header, filesize)?,
+        };
+        header.set_endian(self.endian);
+        Ok(header)
+    }
+
     /// Add data to Cpio Archive
+    ///
+    /// Returns [`CpioError::UnexpectedData`] if `reader` isn't empty and the file type of
+    /// `header` can't contain data, such as a directory.
     pub fn push_file(
         &mut self,
         mut reader: impl ReadSeek + 'a + 'static,
@@ -320,65 +845,310 @@ impl<'a, C: CpioHeader + Debug> ArchiveWriter<'a, C> {
         // stream_len
         let filesize = reader.seek(SeekFrom::End(0))?;
         reader.seek(SeekFrom::Start(0))?;
+        if let Some(file_type) = header.file_type() {
+            if filesize != 0 && !file_type.has_data() {
+                return Err(CpioError::UnexpectedData(file_type));
+            }
+        }
 
-        let header = C::from_header(header, filesize);
+        let header = self.format_header(header, filesize)?;
         let object = Object::new(header, Data::Reader(Box::new(reader)));
-        self.objects.inner.push(object);
-
-        Ok(())
+        self.push_object(object)
     }
 
     /// Add Empty File (Directory) to Cpio Archive
+    ///
+    /// Returns [`CpioError::MissingData`] if `header` is a symlink, which requires a target.
     pub fn push_empty(&mut self, header: Header) -> Result<(), CpioError> {
-        let header = C::from_header(header, 0);
+        if header.file_type() == Some(FileType::Symlink) {
+            return Err(CpioError::MissingData(FileType::Symlink));
+        }
+        let header = self.format_header(header, 0)?;
         let object = Object::new(header, Data::Empty);
-        self.objects.inner.push(object);
+        self.push_object(object)
+    }
 
-        Ok(())
+    /// Add symlink to `target`, setting the file type of `header`
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::io::Cursor;
+    /// # use librarium::{ArchiveWriter, FileType, Header, NewcHeader, Permissions};
+    /// let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(Cursor::new(vec![])));
+    /// let dir = Header::new("usr/bin", FileType::Directory, Permissions::from_bits(0o755));
+    /// writer.push_dir(dir).unwrap();
+    /// let sh = Header::new("bin/sh", FileType::Symlink, Permissions::from_bits(0o777));
+    /// writer.push_symlink(sh, "busybox").unwrap();
+    /// let console = Header { name: "dev/console".into(), mode: 0o600, ..Header::default() };
+    /// writer.push_char_device(console, 5, 1).unwrap();
+    /// writer.write().unwrap();
+    /// ```
+    pub fn push_symlink(
+        &mut self,
+        mut header: Header,
+        target: impl AsRef<[u8]>,
+    ) -> Result<(), CpioError> {
+        header.set_file_type(FileType::Symlink);
+        let target = target.as_ref();
+        if target.is_empty() {
+            return Err(CpioError::MissingData(FileType::Symlink));
+        }
+        self.push_file(Cursor::new(target.to_vec()), header)
     }
 
-    /// Finalize and image and write to writer, adding a trailing `TRAILER!!!` entry.
-    pub fn write(&mut self) -> Result<(), CpioError> {
-        let header = Header { nlink: 1, name: "TRAILER!!!".to_string(), ..Default::default() };
+    /// Add directory, setting the file type of `header`
+    pub fn push_dir(&mut self, mut header: Header) -> Result<(), CpioError> {
+        header.set_file_type(FileType::Directory);
+        self.push_empty(header)
+    }
+
+    /// Add FIFO, setting the file type of `header`
+    pub fn push_fifo(&mut self, mut header: Header) -> Result<(), CpioError> {
+        header.set_file_type(FileType::Fifo);
+        self.push_empty(header)
+    }
 
-        // empty data
-        let data = Cursor::new(vec![]);
-        self.push_file(data, header)?;
+    /// Add character device with device number `major`:`minor`, see [`Self::push_block_device`]
+    pub fn push_char_device(
+        &mut self,
+        header: Header,
+        major: u32,
+        minor: u32,
+    ) -> Result<(), CpioError> {
+        self.push_device(header, FileType::CharDevice, major, minor)
+    }
+
+    /// Add block device with device number `major`:`minor`, setting the file type of `header`
+    ///
+    /// newc and crc headers store `major` and `minor` in separate fields. odc and bin headers
+    /// store both in `rdev`, with an 8 bit `minor` number and `rdev` limited to 18 and 16 bits.
+    /// Returns [`CpioError::DeviceOutOfRange`] if the device number can't be stored in the header.
+    pub fn push_block_device(
+        &mut self,
+        header: Header,
+        major: u32,
+        minor: u32,
+    ) -> Result<(), CpioError> {
+        self.push_device(header, FileType::BlockDevice, major, minor)
+    }
 
+    fn push_device(
+        &mut self,
+        mut header: Header,
+        file_type: FileType,
+        major: u32,
+        minor: u32,
+    ) -> Result<(), CpioError> {
+        header.set_file_type(file_type);
+        header.rdev = None;
+        header.rdevmajor = Some(major);
+        header.rdevminor = Some(minor);
+
+        let header = self.format_header(header, 0).map_err(|e| match e {
+            CpioError::FieldOverflow { field: "rdev", .. } => {
+                CpioError::DeviceOutOfRange { major, minor }
+            }
+            e => e,
+        })?;
+        if header.device() != Some((major, minor)) {
+            return Err(CpioError::DeviceOutOfRange { major, minor });
+        }
+        self.push_object(Object::new(header, Data::Empty))
+    }
+
+    fn push_object(&mut self, mut object: Object<C>) -> Result<(), CpioError> {
+        if self.streaming {
+            self.write_object(&mut object)
+        } else {
+            self.objects.inner.push(object);
+            Ok(())
+        }
+    }
+
+    /// Set `nlink` and move the data of hard links, returning the data of files with data on
+    /// every link
+    fn link_objects(&mut self) -> Result<BTreeMap<link::LinkKey, Box<dyn ReadSeek>>, CpioError> {
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (i, object) in self.objects.inner.iter().enumerate() {
+            if let Some(key) = link::key(&object.header) {
+                groups.entry(key).or_default().push(i);
+            }
+        }
+
+        let mut shared = BTreeMap::new();
+        for (key, links) in groups {
+            // only link left, written with its own data
+            if let [i] = links[..] {
+                self.objects.inner[i].header.set_nlink(1)?;
+                continue;
+            }
+            let mut filesize = 0;
+            let mut data = None;
+            for &i in &links {
+                let object = &mut self.objects.inner[i];
+                object.header.set_nlink(u32::try_from(links.len()).unwrap_or(u32::MAX))?;
+                if object.header.filesize() != 0 {
+                    filesize = object.header.filesize();
+                    if let Data::Reader(reader) = core::mem::replace(&mut object.data, Data::Empty)
+                    {
+                        data = Some(reader);
+                    }
+                }
+                object.header.set_filesize(0)?;
+            }
+            let Some(data) = data else {
+                continue;
+            };
+
+            let last = &mut self.objects.inner[links[links.len() - 1]];
+            if last.header.data_on_last_link() {
+                last.header.set_filesize(filesize)?;
+                last.data = Data::Reader(data);
+            } else {
+                for &i in &links {
+                    self.objects.inner[i].header.set_filesize(filesize)?;
+                }
+                shared.insert(key, data);
+            }
+        }
+
+        Ok(shared)
+    }
+
+    /// Sort objects by normalized path and renumber `ino` in the sorted order
+    fn sort_objects(&mut self) -> Result<(), CpioError> {
+        let objects = &mut self.objects.inner;
+        objects.sort_by_cached_key(|object| normalize_path(object.header.name_bytes()));
+
+        // links share the number assigned when pushed
+        let mut inodes = BTreeMap::new();
+        for object in objects {
+            let next = inodes.len() as u32 + 1;
+            let ino = *inodes.entry(object.header.ino()).or_insert(next);
+            object.header.set_ino(ino)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_object(&mut self, object: &mut Object<C>) -> Result<(), CpioError> {
         let mut writer = Writer::new(&mut self.writer);
-        self.objects.to_mutwriter(&mut writer, ()).unwrap();
+        object.to_mutwriter(&mut writer, ())?;
+        self.bytes_written += (writer.bits_written / 8) as u64;
 
-        // pad bytes if required
-        let bytes_used = (writer.bits_written / 8) as u64;
-        if self.pad_len != 0 {
-            // Pad out block_size to 4K
-            let blocks_used: u32 = u32::try_from(bytes_used).unwrap() / self.pad_len;
-            let total_pad_len = (blocks_used + 1) * self.pad_len;
-            let pad_len = total_pad_len - u32::try_from(bytes_used).unwrap();
-
-            // Write 1K at a time
-            let mut total_written = 0;
-            while ((writer.bits_written / 8) as u64) < (bytes_used + u64::from(pad_len)) {
-                let arr = &[0x00; 1024];
-
-                // check if last block to write
-                let len = if (pad_len - total_written) < 1024 {
-                    (pad_len - total_written) % 1024
-                } else {
-                    // else, full 1K
-                    1024
-                };
+        Ok(())
+    }
 
-                writer.write_bytes(&arr[..len.try_into().unwrap()])?;
-                total_written += len;
+    /// Finalize and image and write to writer, adding a trailing `TRAILER!!!` entry.
+    pub fn write(&mut self) -> Result<(), CpioError> {
+        if self.reproducible.is_some() {
+            self.sort_objects()?;
+        }
+        // not numbered or changed by reproducible options
+        let trailer =
+            self.new_header(Header { nlink: 1, name: TRAILER.into(), ..Default::default() }, 0)?;
+        self.push_object(Object::new(trailer, Data::Empty))?;
+
+        let mut shared = self.link_objects()?;
+        let objects = core::mem::take(&mut self.objects.inner);
+        for mut object in objects {
+            // data repeated for every link
+            let key = link::key(&object.header);
+            if let Some(mut reader) = key.and_then(|key| shared.remove(&key)) {
+                reader.seek(SeekFrom::Start(0))?;
+                object.data = Data::Reader(reader);
+            }
+            self.write_object(&mut object)?;
+            if let (Some(key), Data::Reader(reader)) = (key, object.data) {
+                shared.insert(key, reader);
             }
         }
 
+        // pad bytes if required
+        let mut remaining = 0;
+        if self.pad_len != 0 {
+            // Pad out to next multiple of pad_len
+            let pad_len = u64::from(self.pad_len);
+            remaining = pad_len - (self.bytes_written % pad_len);
+        }
+        // overwrite the rest of an archive appended to
+        remaining = remaining.max(self.append_end.saturating_sub(self.bytes_written));
+
+        // Write 1K at a time
+        let arr = &[0x00; 1024];
+        while remaining != 0 {
+            let len = remaining.min(arr.len() as u64);
+            self.writer.write_all(&arr[..len as usize])?;
+            self.bytes_written += len;
+            remaining -= len;
+        }
+        self.writer.finish()?;
+
         Ok(())
     }
+
+    /// Finalize image, same as [`Self::write`] but consuming the `ArchiveWriter`
+    pub fn finish(mut self) -> Result<(), CpioError> {
+        self.write()
+    }
+}
+
+/// Output of [`ArchiveWriter`]
+enum Output<'a> {
+    Plain(Box<dyn WriteSeek + 'a>),
+    #[cfg(feature = "std")]
+    Compressed(compression::CompressWriter<'a>),
+}
+
+impl Output<'_> {
+    /// Write the end of compressed data
+    fn finish(&mut self) -> Result<(), no_std_io2::io::Error> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            #[cfg(feature = "std")]
+            Self::Compressed(writer) => writer.finish(),
+        }
+    }
+}
+
+impl Write for Output<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, no_std_io2::io::Error> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "std")]
+            Self::Compressed(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), no_std_io2::io::Error> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            #[cfg(feature = "std")]
+            Self::Compressed(writer) => writer.flush(),
+        }
+    }
+}
+
+impl Seek for Output<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, no_std_io2::io::Error> {
+        match self {
+            Self::Plain(writer) => writer.seek(pos),
+            #[cfg(feature = "std")]
+            Self::Compressed(writer) => writer.seek(pos),
+        }
+    }
 }
 
 /// Common representation of cpio Header
+///
+/// # Example
+/// ```rust
+/// # use librarium::{FileType, Header, Permissions};
+/// let header = Header::new("bin", FileType::Directory, Permissions::from_bits(0o755));
+/// assert_eq!(header.mode, 0o40755);
+/// assert_eq!(header.file_type(), Some(FileType::Directory));
+/// assert!(header.permissions().contains(Permissions::GROUP_EXECUTE));
+/// ```
 #[derive(Default, Debug, PartialEq, Eq)]
 pub struct Header {
     pub ino: u32,
@@ -386,14 +1156,58 @@ pub struct Header {
     pub uid: u32,
     pub gid: u32,
     pub nlink: u32,
-    pub mtime: u32,
+    pub mtime: u64,
     pub dev: Option<u32>,
     pub devmajor: Option<u32>,
     pub devminor: Option<u32>,
     pub rdev: Option<u32>,
     pub rdevmajor: Option<u32>,
     pub rdevminor: Option<u32>,
-    pub name: String,
+    /// Name as bytes, without the NUL terminator, usually a path relative to the root
+    pub name: Vec<u8>,
+}
+
+impl Header {
+    /// Create new `Header` of `file_type` with `permissions` and one link
+    pub fn new(name: impl Into<Vec<u8>>, file_type: FileType, permissions: Permissions) -> Self {
+        Self {
+            mode: file_type.bits() | permissions.bits(),
+            nlink: 1,
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Name for display, with invalid UTF-8 replaced by `U+FFFD`
+    pub fn name_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.name)
+    }
+
+    /// Name as `OsStr`, for paths on the filesystem
+    #[cfg(all(feature = "std", unix))]
+    pub fn name_os_str(&self) -> &std::ffi::OsStr {
+        std::os::unix::ffi::OsStrExt::from_bytes(&self.name)
+    }
+
+    /// File type of `mode`, `None` if not set or unknown
+    pub fn file_type(&self) -> Option<FileType> {
+        FileType::from_mode(self.mode)
+    }
+
+    /// Permission bits of `mode`
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_bits(self.mode)
+    }
+
+    /// Set file type bits of `mode`, keeping the permission bits
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        self.mode = (self.mode & !mode::S_IFMT) | file_type.bits();
+    }
+
+    /// Set permission bits of `mode`, keeping the file type bits
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        self.mode = (self.mode & mode::S_IFMT) | permissions.bits();
+    }
 }
 
 /// Object in cpio archive
@@ -412,6 +1226,43 @@ impl<C: CpioHeader> Object<C> {
         let data_pad = vec![0; header.data_pad()];
         Self { header, data, data_pad }
     }
+
+    /// Target of symlink, read from the data of the object with `reader`, `None` if the object
+    /// isn't a symlink
+    ///
+    /// The target is returned as bytes, as it doesn't need to be valid UTF-8. Returns an
+    /// `InvalidData` error if the target is longer than [`SYMLINK_MAX`] bytes.
+    ///
+    /// # Example
+    /// ```rust, no_run
+    /// # use librarium::{ArchiveReader, CpioHeader, NewcHeader};
+    /// # use std::fs::File;
+    /// let file = File::open("archive.cpio").unwrap();
+    /// let mut archive = ArchiveReader::<NewcHeader>::from_reader(file).unwrap();
+    /// for object in &archive.objects.inner {
+    ///     if let Some(target) = object.symlink_target(&mut archive.reader).unwrap() {
+    ///         let target = String::from_utf8_lossy(&target);
+    ///         println!("{} -> {target}", object.header.name_lossy());
+    ///     } else if let Some((major, minor)) = object.header.device() {
+    ///         println!("{} {major}:{minor}", object.header.name_lossy());
+    ///     }
+    /// }
+    /// ```
+    pub fn symlink_target<R: ReadSeek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Option<Vec<u8>>, CpioError> {
+        if self.header.file_type() != Some(FileType::Symlink) {
+            return Ok(None);
+        }
+        if self.header.filesize() > u64::from(SYMLINK_MAX) {
+            return Err(CpioError::StdIo(ErrorKind::InvalidData.into()));
+        }
+        let mut target = Vec::new();
+        reader.extract_data(self, &mut target)?;
+
+        Ok(Some(target))
+    }
 }
 
 impl<C: CpioHeader> MutWriter for Object<C> {
@@ -420,6 +1271,11 @@ impl<C: CpioHeader> MutWriter for Object<C> {
         deku_writer: &mut Writer<W>,
         _: (),
     ) -> core::result::Result<(), DekuError> {
+        if self.header.has_checksum() {
+            log::trace!("calculating checksum");
+            let check = self.data.checksum().map_err(|_| DekuError::Io(ErrorKind::Other))?;
+            self.header.set_check(check);
+        }
         log::trace!("writing header");
         DekuWriter::to_writer(&self.header, deku_writer, ())?;
         log::trace!("writing data, {}", self.header.filesize());
@@ -433,9 +1289,9 @@ impl<C: CpioHeader> MutWriter for Object<C> {
     }
 }
 
-trait OctalConversion {
+trait OctalConversion: Sized {
     fn to_octal_bytes(&self, n: usize) -> Vec<u8>;
-    fn from_octal_string(s: &str) -> Self;
+    fn from_octal_string(s: &str) -> Option<Self>;
 }
 
 impl<T> OctalConversion for T
@@ -467,10 +1323,7 @@ where
     }
 
     // Convert an octal string back to the integer type
-    fn from_octal_string(s: &str) -> Self {
-        match T::from_str_radix(s, 8) {
-            Ok(value) => value,
-            Err(_) => T::zero(), // Or handle the error appropriately
-        }
+    fn from_octal_string(s: &str) -> Option<Self> {
+        T::from_str_radix(s, 8).ok()
     }
 }
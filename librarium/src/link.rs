@@ -0,0 +1,84 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{CpioHeader, FileType, Header, Object};
+
+/// Hard links to the same file in an archive
+///
+/// newc and crc archives only store the data on the last link, with the other links having a
+/// `filesize` of 0. odc and bin archives store the data on every link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkGroup {
+    /// Indexes of the links in the objects, in archive order
+    pub links: Vec<usize>,
+    /// Index of the object containing the data of the file, `None` if all links are empty
+    pub data: Option<usize>,
+}
+
+/// Identity of the file of a hard link, equal for all links to the same file
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct LinkKey {
+    dev: (u32, u32, u32),
+    ino: u32,
+}
+
+/// Key of `header` if it is a regular file with more than one link
+pub(crate) fn key<C: CpioHeader>(header: &C) -> Option<LinkKey> {
+    if header.file_type() != Some(FileType::Regular) || header.nlink() < 2 {
+        return None;
+    }
+    let dev =
+        (header.dev().unwrap_or(0), header.devmajor().unwrap_or(0), header.devminor().unwrap_or(0));
+
+    Some(LinkKey { dev, ino: header.ino() })
+}
+
+/// Key of `header` before it is converted to a format, see [`key`]
+pub(crate) fn header_key(header: &Header) -> Option<LinkKey> {
+    if header.file_type() != Some(FileType::Regular) || header.nlink < 2 {
+        return None;
+    }
+    let dev = (header.dev.unwrap_or(0), header.devmajor.unwrap_or(0), header.devminor.unwrap_or(0));
+
+    Some(LinkKey { dev, ino: header.ino })
+}
+
+/// Groups of hard links in `objects` with more than one link, in order of the first link
+pub fn link_groups<C: CpioHeader>(objects: &[Object<C>]) -> Vec<LinkGroup> {
+    let mut groups: BTreeMap<LinkKey, LinkGroup> = BTreeMap::new();
+    for (i, object) in objects.iter().enumerate() {
+        if let Some(key) = key(&object.header) {
+            let group = groups.entry(key).or_insert(LinkGroup { links: Vec::new(), data: None });
+            group.links.push(i);
+            if object.header.filesize() != 0 {
+                group.data = Some(i);
+            }
+        }
+    }
+
+    let mut groups: Vec<_> = groups.into_values().filter(|group| group.links.len() > 1).collect();
+    groups.sort_unstable_by_key(|group| group.links[0]);
+    groups
+}
+
+/// Object containing the data of `object` in `objects`
+///
+/// If `object` is an empty hard link, this is the last link to the same file with data.
+pub(crate) fn resolve<'a, C: CpioHeader>(
+    objects: &'a [Object<C>],
+    object: &'a Object<C>,
+) -> &'a Object<C> {
+    if object.header.filesize() != 0 {
+        return object;
+    }
+    let Some(key) = key(&object.header) else {
+        return object;
+    };
+    objects
+        .iter()
+        .rev()
+        .find(|other| other.header.filesize() != 0 && self::key(&other.header) == Some(key))
+        .unwrap_or(object)
+}
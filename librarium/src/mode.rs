@@ -0,0 +1,150 @@
+use core::fmt;
+use core::ops::{BitAnd, BitOr, BitOrAssign};
+
+/// Mask of the file type bits of `mode`
+pub(crate) const S_IFMT: u32 = 0o170000;
+/// Mask of the permission bits of `mode`
+const PERMISSIONS_MASK: u32 = 0o7777;
+
+/// Type of file, stored in the `S_IFMT` bits of `mode`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FileType {
+    Regular,
+    Directory,
+    Symlink,
+    CharDevice,
+    BlockDevice,
+    Fifo,
+    Socket,
+}
+
+impl FileType {
+    /// File type bits of `mode`
+    pub const fn bits(self) -> u32 {
+        match self {
+            Self::Socket => 0o140000,
+            Self::Symlink => 0o120000,
+            Self::Regular => 0o100000,
+            Self::BlockDevice => 0o060000,
+            Self::Directory => 0o040000,
+            Self::CharDevice => 0o020000,
+            Self::Fifo => 0o010000,
+        }
+    }
+
+    /// File type of `mode`, `None` if the file type bits are unknown or not set
+    pub const fn from_mode(mode: u32) -> Option<Self> {
+        match mode & S_IFMT {
+            0o140000 => Some(Self::Socket),
+            0o120000 => Some(Self::Symlink),
+            0o100000 => Some(Self::Regular),
+            0o060000 => Some(Self::BlockDevice),
+            0o040000 => Some(Self::Directory),
+            0o020000 => Some(Self::CharDevice),
+            0o010000 => Some(Self::Fifo),
+            _ => None,
+        }
+    }
+
+    /// Objects of this type can contain data, such as the contents of a file or the target of a
+    /// symlink
+    pub const fn has_data(self) -> bool {
+        matches!(self, Self::Regular | Self::Symlink)
+    }
+
+    /// FIFO, device or socket, created with `rdev` instead of data
+    pub const fn is_special(self) -> bool {
+        matches!(self, Self::CharDevice | Self::BlockDevice | Self::Fifo | Self::Socket)
+    }
+}
+
+/// Permission bits of `mode`, including the setuid, setgid and sticky bits
+///
+/// # Example
+/// ```rust
+/// # use librarium::Permissions;
+/// let permissions = Permissions::from_bits(0o755);
+/// assert!(permissions.contains(Permissions::OWNER_WRITE | Permissions::OTHER_EXECUTE));
+/// assert!(!permissions.contains(Permissions::SETUID));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Permissions(u32);
+
+impl Permissions {
+    pub const SETUID: Self = Self(0o4000);
+    pub const SETGID: Self = Self(0o2000);
+    pub const STICKY: Self = Self(0o1000);
+    pub const OWNER_READ: Self = Self(0o400);
+    pub const OWNER_WRITE: Self = Self(0o200);
+    pub const OWNER_EXECUTE: Self = Self(0o100);
+    pub const GROUP_READ: Self = Self(0o040);
+    pub const GROUP_WRITE: Self = Self(0o020);
+    pub const GROUP_EXECUTE: Self = Self(0o010);
+    pub const OTHER_READ: Self = Self(0o004);
+    pub const OTHER_WRITE: Self = Self(0o002);
+    pub const OTHER_EXECUTE: Self = Self(0o001);
+
+    /// Permissions from the permission bits of `mode`, ignoring other bits
+    pub const fn from_bits(mode: u32) -> Self {
+        Self(mode & PERMISSIONS_MASK)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// All bits of `other` are set
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl fmt::Debug for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Permissions({:#o})", self.0)
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Permissions {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Permissions {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_type() {
+        for file_type in [
+            FileType::Regular,
+            FileType::Directory,
+            FileType::Symlink,
+            FileType::CharDevice,
+            FileType::BlockDevice,
+            FileType::Fifo,
+            FileType::Socket,
+        ] {
+            assert_eq!(FileType::from_mode(file_type.bits() | 0o755), Some(file_type));
+        }
+        assert_eq!(FileType::from_mode(0o644), None);
+        assert_eq!(Permissions::from_bits(0o41777).bits(), 0o1777);
+    }
+}
@@ -1,21 +1,29 @@
-use crate::{CpioHeader, Header};
-use core::ffi::CStr;
+use crate::cpio_header::{fit, read_name, trim_nul, with_nul};
+use crate::error::INVALID_HEX;
+use crate::{CpioError, CpioHeader, Format, Header};
 use deku::prelude::*;
 use no_std_io2::io::{Read, Seek, Write};
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
 #[cfg(feature = "alloc")]
-use alloc::{string::ToString, vec, vec::Vec};
+use alloc::{vec, vec::Vec};
 
 const NEWC_MAGIC: [u8; 6] = [b'0', b'7', b'0', b'7', b'0', b'1'];
+const CRC_MAGIC: [u8; 6] = [b'0', b'7', b'0', b'7', b'0', b'2'];
 // Size of magic field in bytes, derived from DekuSize
-const MAGIC_SIZE_BYTES: usize = <[u8; 6]>::SIZE_BYTES.unwrap();
+pub(crate) const MAGIC_SIZE_BYTES: usize = <[u8; 6]>::SIZE_BYTES.unwrap();
 
 /// Improved cpio Header, also known as "SVR4" or "New ASCII"
+pub type NewcHeader = Svr4Header<false>;
+
+/// Header of the newc and crc formats, see [`NewcHeader`] and [`CrcHeader`](crate::CrcHeader)
+///
+/// Both formats have the same layout, with `CRC` selecting the magic and whether `check` contains
+/// the checksum of the file data.
 #[derive(DekuWrite, DekuRead, Debug)]
-pub struct NewcHeader {
-    #[deku(assert_eq = "NEWC_MAGIC")]
+pub struct Svr4Header<const CRC: bool> {
+    #[deku(assert_eq = "Self::MAGIC")]
     magic: [u8; 6],
     ino: Ascii,
     mode: Ascii,
@@ -30,34 +38,40 @@ pub struct NewcHeader {
     rdevminor: Ascii,
     namesize: Ascii,
     check: Ascii,
-    #[deku(count = "namesize.value")]
+    #[deku(reader = "read_name(deku::reader, namesize.value as usize)")]
     name: Vec<u8>,
     #[deku(count = "pad_to_4(MAGIC_SIZE_BYTES + namesize.value as usize)")]
     name_pad: Vec<u8>,
 }
 
-impl CpioHeader for NewcHeader {
-    fn from_header(header: Header, filesize: u64) -> Self {
-        let name_bytes = header.name.into_bytes();
+impl<const CRC: bool> Svr4Header<CRC> {
+    const MAGIC: [u8; 6] = if CRC { CRC_MAGIC } else { NEWC_MAGIC };
+    const FORMAT: Format = if CRC { Format::Crc } else { Format::Newc };
+}
+
+impl<const CRC: bool> CpioHeader for Svr4Header<CRC> {
+    fn from_header(header: Header, filesize: u64) -> Result<Self, CpioError> {
+        let name_bytes = with_nul(header.name)?;
         let name_len = name_bytes.len();
-        NewcHeader {
-            magic: NEWC_MAGIC,
+        Ok(Self {
+            magic: Self::MAGIC,
             ino: Ascii::new(header.ino),
             mode: Ascii::new(header.mode),
             uid: Ascii::new(header.uid),
             gid: Ascii::new(header.gid),
             nlink: Ascii::new(header.nlink),
-            mtime: Ascii::new(header.mtime),
-            filesize: Ascii::new(u32::try_from(filesize).unwrap()),
+            mtime: Ascii::new(fit(Self::FORMAT, "mtime", header.mtime)?),
+            filesize: Ascii::new(fit(Self::FORMAT, "filesize", filesize)?),
             devmajor: Ascii::new(header.devmajor.unwrap_or(0)),
             devminor: Ascii::new(header.devminor.unwrap_or(0)),
             rdevmajor: Ascii::new(header.rdevmajor.unwrap_or(0)),
             rdevminor: Ascii::new(header.rdevminor.unwrap_or(0)),
-            namesize: Ascii::new(name_len as u32 + 1),
+            namesize: Ascii::new(name_len as u32),
+            // calculated from data when written
             check: Ascii::new(0),
-            name: name_bytes.to_vec(),
+            name: name_bytes,
             name_pad: vec![0; pad_to_4(MAGIC_SIZE_BYTES + name_len)],
-        }
+        })
     }
 
     fn as_header(&self) -> Header {
@@ -74,7 +88,7 @@ impl CpioHeader for NewcHeader {
             rdev: self.rdev(),
             rdevmajor: self.rdevmajor(),
             rdevminor: self.rdevminor(),
-            name: self.name().to_string(),
+            name: self.name_bytes().to_vec(),
         }
     }
 
@@ -98,12 +112,31 @@ impl CpioHeader for NewcHeader {
         self.nlink.value
     }
 
-    fn mtime(&self) -> u32 {
-        self.mtime.value
+    fn mtime(&self) -> u64 {
+        self.mtime.value.into()
     }
 
-    fn filesize(&self) -> u32 {
-        self.filesize.value
+    fn filesize(&self) -> u64 {
+        self.filesize.value.into()
+    }
+
+    fn set_ino(&mut self, ino: u32) -> Result<(), CpioError> {
+        self.ino = Ascii::new(ino);
+        Ok(())
+    }
+
+    fn set_nlink(&mut self, nlink: u32) -> Result<(), CpioError> {
+        self.nlink = Ascii::new(nlink);
+        Ok(())
+    }
+
+    fn set_filesize(&mut self, filesize: u64) -> Result<(), CpioError> {
+        self.filesize = Ascii::new(fit(Self::FORMAT, "filesize", filesize)?);
+        Ok(())
+    }
+
+    fn data_on_last_link(&self) -> bool {
+        true
     }
 
     fn dev(&self) -> Option<u32> {
@@ -138,17 +171,27 @@ impl CpioHeader for NewcHeader {
         Some(self.check.value)
     }
 
-    fn name(&self) -> &str {
-        CStr::from_bytes_with_nul(&self.name).unwrap().to_str().unwrap()
+    fn has_checksum(&self) -> bool {
+        CRC
+    }
+
+    fn set_check(&mut self, check: u32) {
+        if CRC {
+            self.check = Ascii::new(check);
+        }
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        trim_nul(&self.name)
     }
 
     fn data_pad(&self) -> usize {
-        pad_to_4(self.filesize() as usize)
+        pad_to_4((self.filesize() % 4) as usize)
     }
 }
 
 /// pad out to a multiple of 4 bytes
-fn pad_to_4(len: usize) -> usize {
+pub(crate) fn pad_to_4(len: usize) -> usize {
     match len % 4 {
         0 => 0,
         x => 4 - x,
@@ -156,7 +199,7 @@ fn pad_to_4(len: usize) -> usize {
 }
 
 #[derive(DekuWrite, DekuRead, DekuSize, Debug, Copy, Clone, Default)]
-struct Ascii {
+pub(crate) struct Ascii {
     #[deku(reader = "Self::read(deku::reader)", writer = "self.write(deku::writer)")]
     pub value: u32,
 }
@@ -172,9 +215,14 @@ impl Ascii {
     fn read<R: Read + Seek>(reader: &mut Reader<R>) -> Result<u32, DekuError> {
         let value = <[u8; 8]>::from_reader_with_ctx(reader, ())?;
         log::debug!("{:02x?}", value);
-        let s = core::str::from_utf8(&value).unwrap();
+        if !value.iter().all(u8::is_ascii_hexdigit) {
+            return Err(deku::deku_error!(DekuError::Parse, INVALID_HEX));
+        }
+        let s = core::str::from_utf8(&value)
+            .map_err(|_| deku::deku_error!(DekuError::Parse, INVALID_HEX))?;
         log::debug!("{}", s);
-        let value = u32::from_str_radix(s, 16).unwrap();
+        let value = u32::from_str_radix(s, 16)
+            .map_err(|_| deku::deku_error!(DekuError::Parse, INVALID_HEX))?;
         log::debug!("{:02x?}", value);
         Ok(value)
     }
@@ -208,4 +256,11 @@ mod tests {
         let written = a.to_bytes().unwrap();
         assert_eq!(*bytes, *written);
     }
+
+    #[test]
+    fn test_acsii_invalid() {
+        for bytes in [b"0000000g", b"+0000001", b"0000 001"] {
+            assert!(Ascii::from_bytes((bytes, 0)).is_err());
+        }
+    }
 }
@@ -1,5 +1,6 @@
-use crate::{CpioHeader, Header, OctalConversion};
-use core::ffi::CStr;
+use crate::cpio_header::{fit, pack_dev, read_name, trim_nul, with_nul};
+use crate::error::INVALID_OCTAL;
+use crate::{CpioError, CpioHeader, Format, Header, OctalConversion};
 use core::fmt;
 use core::str;
 use deku::prelude::*;
@@ -8,7 +9,7 @@ use no_std_io2::io::{Read, Seek, Write};
 #[cfg(feature = "alloc")]
 extern crate alloc;
 #[cfg(feature = "alloc")]
-use alloc::{ffi::CString, string::ToString, vec::Vec};
+use alloc::vec::Vec;
 
 const ODC_MAGIC: &[u8] = b"070707";
 
@@ -28,29 +29,32 @@ pub struct OdcHeader {
     mtime: Octal<u64, 11>,
     namesize: Octal<u32, 6>,
     filesize: Octal<u64, 11>,
-    #[deku(count = "namesize.value")]
+    #[deku(reader = "read_name(deku::reader, namesize.value as usize)")]
     name: Vec<u8>,
 }
 
 impl CpioHeader for OdcHeader {
-    fn from_header(header: Header, filesize: u64) -> Self {
-        let name_bytes = header.name.into_bytes();
-        let name_len = name_bytes.len() + 1;
-
-        Self {
+    fn from_header(header: Header, filesize: u64) -> Result<Self, CpioError> {
+        let name_bytes = with_nul(header.name)?;
+        let name_len = name_bytes.len();
+        let rdev = header.rdev.unwrap_or_else(|| {
+            pack_dev(header.rdevmajor.unwrap_or(0), header.rdevminor.unwrap_or(0))
+        });
+
+        Ok(Self {
             magic: ODC_MAGIC.try_into().unwrap(),
-            dev: Octal::new(header.dev.unwrap_or(0)),
-            ino: Octal::new(header.ino),
-            mode: Octal::new(header.mode),
-            uid: Octal::new(header.uid),
-            gid: Octal::new(header.gid),
-            nlink: Octal::new(header.nlink),
-            rdev: Octal::new(header.rdev.unwrap_or(0)),
-            mtime: Octal::new(header.mtime.into()),
-            namesize: Octal::new(name_len as u32),
-            filesize: Octal::new(filesize),
-            name: CString::new(name_bytes).unwrap().into_bytes_with_nul(),
-        }
+            dev: Octal::checked("dev", header.dev.unwrap_or(0))?,
+            ino: Octal::checked("ino", header.ino)?,
+            mode: Octal::checked("mode", header.mode)?,
+            uid: Octal::checked("uid", header.uid)?,
+            gid: Octal::checked("gid", header.gid)?,
+            nlink: Octal::checked("nlink", header.nlink)?,
+            rdev: Octal::checked("rdev", rdev)?,
+            mtime: Octal::checked("mtime", header.mtime)?,
+            namesize: Octal::checked("namesize", name_len as u64)?,
+            filesize: Octal::checked("filesize", filesize)?,
+            name: name_bytes,
+        })
     }
 
     fn as_header(&self) -> Header {
@@ -67,7 +71,7 @@ impl CpioHeader for OdcHeader {
             rdev: self.rdev(),
             rdevmajor: self.rdevmajor(),
             rdevminor: self.rdevminor(),
-            name: self.name().to_string(),
+            name: self.name_bytes().to_vec(),
         }
     }
 
@@ -91,12 +95,27 @@ impl CpioHeader for OdcHeader {
         self.nlink.value
     }
 
-    fn mtime(&self) -> u32 {
-        self.mtime.value as u32
+    fn mtime(&self) -> u64 {
+        self.mtime.value
+    }
+
+    fn filesize(&self) -> u64 {
+        self.filesize.value
+    }
+
+    fn set_ino(&mut self, ino: u32) -> Result<(), CpioError> {
+        self.ino = Octal::checked("ino", ino)?;
+        Ok(())
+    }
+
+    fn set_nlink(&mut self, nlink: u32) -> Result<(), CpioError> {
+        self.nlink = Octal::checked("nlink", nlink)?;
+        Ok(())
     }
 
-    fn filesize(&self) -> u32 {
-        self.filesize.value as u32
+    fn set_filesize(&mut self, filesize: u64) -> Result<(), CpioError> {
+        self.filesize = Octal::checked("filesize", filesize)?;
+        Ok(())
     }
 
     fn dev(&self) -> Option<u32> {
@@ -131,8 +150,8 @@ impl CpioHeader for OdcHeader {
         None
     }
 
-    fn name(&self) -> &str {
-        CStr::from_bytes_with_nul(&self.name).unwrap().to_str().unwrap()
+    fn name_bytes(&self) -> &[u8] {
+        trim_nul(&self.name)
     }
 
     fn data_pad(&self) -> usize {
@@ -147,15 +166,33 @@ struct Octal<T: OctalConversion + fmt::Debug + DekuSize, const N: usize> {
 }
 
 impl<T: OctalConversion + fmt::Debug + DekuSize, const N: usize> Octal<T, N> {
+    /// Largest value of `N` octal digits
+    const MAX: u64 = (1 << (3 * N)) - 1;
+
     pub fn new(value: T) -> Self {
         Self { value }
     }
 
+    /// `value` of `field`, or [`CpioError::FieldOverflow`] if it has more than `N` octal digits
+    fn checked(field: &'static str, value: impl Into<u64>) -> Result<Self, CpioError>
+    where
+        T: TryFrom<u64>,
+    {
+        let value = value.into();
+        if value > Self::MAX {
+            return Err(CpioError::FieldOverflow { format: Format::Odc, field, value });
+        }
+        fit(Format::Odc, field, value).map(Self::new)
+    }
+
     fn read<R: Read + Seek>(reader: &mut Reader<R>) -> Result<T, DekuError> {
         let value = <[u8; N]>::from_reader_with_ctx(reader, ())?;
-        let s = str::from_utf8(&value).unwrap();
-        let value = T::from_octal_string(s);
-        Ok(value)
+        if !value.iter().all(|b| (b'0'..=b'7').contains(b)) {
+            return Err(deku::deku_error!(DekuError::Parse, INVALID_OCTAL));
+        }
+        let s = str::from_utf8(&value)
+            .map_err(|_| deku::deku_error!(DekuError::Parse, INVALID_OCTAL))?;
+        T::from_octal_string(s).ok_or(deku::deku_error!(DekuError::Parse, INVALID_OCTAL))
     }
 
     fn write<W: Write + Seek>(&self, writer: &mut Writer<W>) -> Result<(), DekuError> {
@@ -177,4 +214,11 @@ mod tests {
         let bytes = dev.to_bytes().unwrap();
         assert_eq!(var_name, &*bytes);
     }
+
+    #[test]
+    fn test_octal_invalid() {
+        for bytes in [b"000008", b"+00001", b"00 001"] {
+            assert!(Octal::<u32, 6>::from_bytes((bytes, 0)).is_err());
+        }
+    }
 }
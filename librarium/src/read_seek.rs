@@ -1,5 +1,13 @@
 use no_std_io2::io::{Read, Seek, SeekFrom};
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(doc)]
+use crate::CpioReader;
+
 /// `Read` + `Seek`
 pub trait ReadSeek: Read + Seek {}
 impl<T: Read + Seek> ReadSeek for T {}
@@ -34,3 +42,128 @@ impl<R: ReadSeek> Seek for ReaderWithOffset<R> {
         self.io.seek(seek).map(|x| x - self.offset)
     }
 }
+
+/// `Read` of the data of one object in an archive, created by [`CpioReader::entry_reader`]
+///
+/// Reads at most the data size of the object from `reader`.
+#[derive(Debug)]
+pub struct EntryReader<'r, R: ?Sized> {
+    reader: &'r mut R,
+    /// Bytes left to read
+    remaining: u64,
+}
+
+impl<'r, R: Read + ?Sized> EntryReader<'r, R> {
+    pub(crate) fn new(reader: &'r mut R, len: u64) -> Self {
+        Self { reader, remaining: len }
+    }
+
+    /// Bytes of data not yet read
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+impl<R: Read + ?Sized> Read for EntryReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, no_std_io2::io::Error> {
+        let len = buf.len().min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        if len == 0 {
+            return Ok(0);
+        }
+        let n = self.reader.read(&mut buf[..len])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Private struct adding forward only `Seek` to a `Read`
+///
+/// Seeking forward reads and discards bytes, seeking backward is only supported within the
+/// last [`Self::LOOKBACK`] bytes read.
+pub(crate) struct ForwardReader<R: Read> {
+    io: R,
+    /// Bytes read from `io`, `buf[..pos]` have been read from `self`
+    buf: Vec<u8>,
+    pos: usize,
+    /// Offset of `buf[0]` from start of `io`
+    buf_offset: u64,
+}
+
+impl<R: Read> ForwardReader<R> {
+    /// Bytes available to seek backwards
+    pub const LOOKBACK: usize = 0x20;
+    const BUF_LEN: usize = 0x2000;
+
+    pub fn new(io: R) -> Self {
+        Self { io, buf: Vec::new(), pos: 0, buf_offset: 0 }
+    }
+
+    /// Current offset from start of `io`
+    pub fn position(&self) -> u64 {
+        self.buf_offset + self.pos as u64
+    }
+
+    pub fn into_inner(self) -> R {
+        self.io
+    }
+
+    /// Read more bytes from `io` into `buf`, keeping `LOOKBACK` bytes. Returns bytes read.
+    fn fill_buf(&mut self) -> Result<usize, no_std_io2::io::Error> {
+        let keep = self.pos.min(Self::LOOKBACK);
+        let drop_len = self.pos - keep;
+        self.buf.drain(..drop_len);
+        self.buf_offset += drop_len as u64;
+        self.pos = keep;
+
+        let len = self.buf.len();
+        self.buf.resize(len + Self::BUF_LEN, 0);
+        let n = loop {
+            match self.io.read(&mut self.buf[len..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == no_std_io2::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buf.truncate(len);
+                    return Err(e);
+                }
+            }
+        };
+        self.buf.truncate(len + n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> Read for ForwardReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, no_std_io2::io::Error> {
+        if self.pos == self.buf.len() && self.fill_buf()? == 0 {
+            return Ok(0);
+        }
+        let n = buf.len().min(self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<R: Read> Seek for ForwardReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, no_std_io2::io::Error> {
+        let target = match pos {
+            SeekFrom::Start(start) => Some(start),
+            SeekFrom::Current(n) => self.position().checked_add_signed(n),
+            SeekFrom::End(_) => None,
+        };
+        let Some(target) = target.filter(|target| *target >= self.buf_offset) else {
+            return Err(no_std_io2::io::ErrorKind::Unsupported.into());
+        };
+
+        // skip forward
+        while target > self.buf_offset + self.buf.len() as u64 {
+            self.pos = self.buf.len();
+            if self.fill_buf()? == 0 {
+                return Err(no_std_io2::io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        self.pos = (target - self.buf_offset) as usize;
+
+        Ok(target)
+    }
+}
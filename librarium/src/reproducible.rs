@@ -0,0 +1,135 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+
+use crate::Header;
+use crate::link::LinkKey;
+
+/// Options for writing reproducible archives, see [`ArchiveWriter::set_reproducible`]
+///
+/// The same files create the same archive, regardless of the order they are pushed in and the
+/// inode numbers, devices and ownership of the files they were read from, as with
+/// `cpio --reproducible` and the kernel's `gen_init_cpio`:
+///
+/// - objects are sorted by normalized path when written, with directories before their contents
+/// - `ino` is renumbered from 1 in archive order, keeping hard links to the same file together
+/// - `dev` is zeroed, the device number `rdev` of devices is kept
+/// - `mtime` is kept, set or clamped, see [`Self::mtime`] and [`Self::clamp_mtime`]
+/// - `uid` and `gid` are kept, or set to root with [`Self::root_ownership`]
+///
+/// [`ArchiveWriter::set_reproducible`]: crate::ArchiveWriter::set_reproducible
+///
+/// # Example
+/// ```rust
+/// # use std::io::Cursor;
+/// # use librarium::{ArchiveWriter, FileType, Header, NewcHeader, Permissions, Reproducible};
+/// let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(Cursor::new(vec![])));
+/// writer.set_reproducible(Reproducible::new().clamp_mtime(1700000000).root_ownership(true))
+///     .unwrap();
+/// let init = Header::new("init", FileType::Regular, Permissions::from_bits(0o755));
+/// writer.push_file(Cursor::new(b"#!/bin/sh\n"), init).unwrap();
+/// writer.write().unwrap();
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Reproducible {
+    mtime: Mtime,
+    root_ownership: bool,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+enum Mtime {
+    #[default]
+    Keep,
+    Set(u64),
+    Clamp(u64),
+}
+
+impl Reproducible {
+    /// Create new `Reproducible`, keeping `mtime`, `uid` and `gid`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clamp `mtime` to `SOURCE_DATE_EPOCH` if it is set in the environment
+    ///
+    /// Returns an error if `SOURCE_DATE_EPOCH` isn't a number of seconds.
+    #[cfg(feature = "std")]
+    pub fn from_env() -> Result<Self, crate::CpioError> {
+        extern crate std;
+        use std::io;
+
+        let reproducible = Self::new();
+        let Some(epoch) = std::env::var_os("SOURCE_DATE_EPOCH") else {
+            return Ok(reproducible);
+        };
+        match epoch.to_str().and_then(|epoch| epoch.parse().ok()) {
+            Some(epoch) => Ok(reproducible.clamp_mtime(epoch)),
+            None => {
+                let msg = std::format!("invalid SOURCE_DATE_EPOCH: {epoch:?}");
+                Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into())
+            }
+        }
+    }
+
+    /// Set `mtime` of all objects to `mtime`
+    pub fn mtime(mut self, mtime: u64) -> Self {
+        self.mtime = Mtime::Set(mtime);
+        self
+    }
+
+    /// Set `mtime` of objects modified after `mtime` to `mtime`, as for `SOURCE_DATE_EPOCH`
+    pub fn clamp_mtime(mut self, mtime: u64) -> Self {
+        self.mtime = Mtime::Clamp(mtime);
+        self
+    }
+
+    /// Set `uid` and `gid` of all objects to 0
+    pub fn root_ownership(mut self, root_ownership: bool) -> Self {
+        self.root_ownership = root_ownership;
+        self
+    }
+
+    /// Apply to `header`, except for `ino` which is assigned by [`Inodes`]
+    pub(crate) fn apply(&self, header: &mut Header) {
+        match self.mtime {
+            Mtime::Keep => (),
+            Mtime::Set(mtime) => header.mtime = mtime,
+            Mtime::Clamp(mtime) => header.mtime = header.mtime.min(mtime),
+        }
+        if self.root_ownership {
+            header.uid = 0;
+            header.gid = 0;
+        }
+        header.dev = None;
+        header.devmajor = None;
+        header.devminor = None;
+    }
+}
+
+/// Sequential inode numbers, shared by hard links to the same file with key `K`
+#[derive(Debug)]
+pub(crate) struct Inodes<K = LinkKey> {
+    links: BTreeMap<K, u32>,
+    last: u32,
+}
+
+impl<K> Default for Inodes<K> {
+    fn default() -> Self {
+        Self { links: BTreeMap::new(), last: 0 }
+    }
+}
+
+impl<K: Ord> Inodes<K> {
+    /// Inode number of the next object, or of the file with `key` if it was already assigned
+    pub(crate) fn assign(&mut self, key: Option<K>) -> u32 {
+        if let Some(ino) = key.as_ref().and_then(|key| self.links.get(key)) {
+            return *ino;
+        }
+        self.last += 1;
+        if let Some(key) = key {
+            self.links.insert(key, self.last);
+        }
+        self.last
+    }
+}
@@ -0,0 +1,206 @@
+use no_std_io2::io::{Seek, SeekFrom, Write};
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, collections::BTreeMap, vec, vec::Vec};
+
+use crate::compression::decompress_at;
+use crate::index::normalize_path;
+use crate::link;
+use crate::{
+    AnyHeader, Compression, CpioError, CpioHeader, CpioReader, Entries, Format, Header, Object,
+    Objects, ReadSeek, ReaderWithOffset, TRAILER,
+};
+
+/// One archive of a [`MultiArchiveReader`]
+pub struct Segment {
+    /// Offset of the first header of the archive
+    pub offset: u64,
+    /// Format of the first header of the archive
+    pub format: Format,
+    /// Compression of the data containing the archive
+    pub compression: Option<Compression>,
+    /// All objects in archive, including the Trailer entry
+    pub objects: Objects<AnyHeader>,
+}
+
+/// Read concatenated cpio Archives, such as Linux initramfs images
+///
+/// Archives can be of any supported [`Format`], and NUL bytes between archives are skipped.
+/// `Data::Offset` of all objects are from the start of `reader`, so the data of any object can be
+/// extracted with `reader`.
+///
+/// Like the kernel, compressed data after an archive is decompressed and read as more archives.
+/// `reader` is then replaced by the data before the compressed data followed by the decompressed
+/// data, see [`Compression`] for the required features. The decompressed data is read in one
+/// pass, but extracting data before the last position read decompresses again from the start of
+/// the compressed data, so extract in archive order.
+///
+/// # Example
+/// List the files of `initrd.img` as extracted by the kernel.
+/// ```rust, no_run
+/// # use librarium::{MultiArchiveReader, CpioHeader};
+/// # use std::fs::File;
+/// let file = File::open("initrd.img").unwrap();
+/// let archive = MultiArchiveReader::from_reader(file).unwrap();
+/// for segment in &archive.segments {
+///     println!("{:?} archive at {:#x}", segment.format, segment.offset);
+/// }
+/// for object in archive.merged() {
+///     println!("{}", object.header.name_lossy());
+/// }
+/// ```
+pub struct MultiArchiveReader<'b> {
+    pub reader: Box<dyn ReadSeek + 'b>,
+    pub segments: Vec<Segment>,
+}
+
+impl<'b> MultiArchiveReader<'b> {
+    /// Read all archives until the end of `reader`
+    ///
+    /// Returns [`CpioError::InvalidMagic`] if data after an archive isn't NUL padding, another
+    /// archive or compressed data.
+    pub fn from_reader(reader: impl ReadSeek + 'b) -> Result<Self, CpioError> {
+        Self::from_reader_with_offset(reader, 0)
+    }
+
+    /// Read all archives from `offset` until the end of `reader`
+    ///
+    /// Offsets are from `offset`.
+    pub fn from_reader_with_offset(
+        reader: impl ReadSeek + 'b,
+        offset: u64,
+    ) -> Result<Self, CpioError> {
+        let mut reader: Box<dyn ReadSeek + 'b> = if offset == 0 {
+            Box::new(reader)
+        } else {
+            Box::new(ReaderWithOffset::new(reader, offset)?)
+        };
+        let mut end = reader.seek(SeekFrom::End(0))?;
+
+        let mut segments = vec![];
+        let mut offset = 0;
+        let mut compression = None;
+        while let Some(start) = skip_padding(&mut reader, offset)? {
+            let format = match Format::detect(&mut reader) {
+                Ok(format) => format,
+                Err(CpioError::UnknownFormat) => {
+                    let Some(c) = Compression::detect(&mut reader)? else {
+                        return Err(CpioError::InvalidMagic { offset: start });
+                    };
+                    reader = decompress_at(reader, start, c)?;
+                    // finding the length would decompress all data before reading the objects
+                    end = u64::MAX;
+                    compression = Some(c);
+                    offset = start;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            log::debug!("{format:?} archive at {start:#x}");
+
+            let mut entries = Entries::<AnyHeader>::starting_at(reader, start, end);
+            let inner = entries.by_ref().collect::<Result<_, _>>()?;
+            offset = entries.offset();
+            reader = entries.reader;
+
+            segments.push(Segment {
+                offset: start,
+                format,
+                compression,
+                objects: Objects { inner },
+            });
+        }
+
+        Ok(Self { reader, segments })
+    }
+
+    /// Objects of all archives as extracted in order, see [`merge`]
+    pub fn merged(&self) -> Vec<&Object<AnyHeader>> {
+        merge(&self.segments)
+    }
+
+    /// Object at `path` in the merged view of all archives, see [`Self::merged`]
+    pub fn get(&self, path: impl AsRef<[u8]>) -> Option<&Object<AnyHeader>> {
+        find(&self.segments, path.as_ref()).map(|(_, object)| object)
+    }
+
+    /// Extract data of object at `path`, see [`Self::get`]
+    ///
+    /// The data of a hard link is extracted from the link containing the data in the same
+    /// archive.
+    pub fn extract_by_name<W>(
+        &mut self,
+        name: impl AsRef<[u8]>,
+        writer: &mut W,
+    ) -> Result<Option<Header>, CpioError>
+    where
+        W: Write,
+    {
+        let Some((segment, object)) = find(&self.segments, name.as_ref()) else {
+            return Ok(None);
+        };
+        self.reader.extract_data(link::resolve(&segment.objects.inner, object), writer)?;
+
+        Ok(Some(object.header.as_header()))
+    }
+}
+
+/// Objects of all `segments` as extracted in order, where an object replaces objects with the
+/// same path in earlier segments
+///
+/// Paths are compared after [`normalize_path`], and the Trailer entries are not included. Only
+/// borrows `segments`, so it can be called while [`MultiArchiveReader::reader`] is borrowed
+/// mutably to extract the data of the objects.
+pub fn merge(segments: &[Segment]) -> Vec<&Object<AnyHeader>> {
+    let mut merged: BTreeMap<_, _> = BTreeMap::new();
+    for (s, segment) in segments.iter().enumerate() {
+        for (i, object) in segment.objects.inner.iter().enumerate() {
+            let name = object.header.name_bytes();
+            if name != TRAILER.as_bytes() {
+                merged.insert(normalize_path(name), (s, i));
+            }
+        }
+    }
+
+    let mut merged: Vec<_> = merged.into_values().collect();
+    merged.sort_unstable();
+    merged.into_iter().map(|(s, i)| &segments[s].objects.inner[i]).collect()
+}
+
+/// Last object at `path` in `segments`, and the segment containing it
+fn find<'a>(segments: &'a [Segment], path: &[u8]) -> Option<(&'a Segment, &'a Object<AnyHeader>)> {
+    let path = normalize_path(path);
+    segments.iter().rev().find_map(|segment| {
+        let object = segment.objects.inner.iter().rev().find(|object| {
+            let name = object.header.name_bytes();
+            name != TRAILER.as_bytes() && normalize_path(name) == path
+        })?;
+        Some((segment, object))
+    })
+}
+
+/// Seek `reader` to the first byte from `offset` that isn't NUL, returning the offset of the
+/// byte or `None` at the end of `reader`
+pub(crate) fn skip_padding(
+    reader: &mut dyn ReadSeek,
+    offset: u64,
+) -> Result<Option<u64>, CpioError> {
+    reader.seek(SeekFrom::Start(offset))?;
+
+    let mut buf = [0; 0x200];
+    let mut offset = offset;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        if let Some(i) = buf[..n].iter().position(|b| *b != 0) {
+            let start = offset + i as u64;
+            reader.seek(SeekFrom::Start(start))?;
+            return Ok(Some(start));
+        }
+        offset += n as u64;
+    }
+}
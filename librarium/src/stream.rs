@@ -0,0 +1,121 @@
+use core::marker::PhantomData;
+
+use deku::prelude::*;
+use no_std_io2::io::{Read, Seek, SeekFrom};
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use crate::read_seek::ForwardReader;
+use crate::{CpioError, CpioHeader, TRAILER};
+
+/// Forward only reader of cpio Archive, for readers that can't `Seek` such as pipes, stdin,
+/// sockets and decompressors
+///
+/// Unlike [`ArchiveReader`](crate::ArchiveReader), data of each entry can only be read while it
+/// is the current entry. Unread data is skipped when advancing to the next entry.
+///
+/// # Example
+/// List all entries of archive piped to stdin, and read data of `init`.
+/// ```rust, no_run
+/// # use std::io::Read;
+/// # use librarium::{StreamReader, AnyHeader, CpioHeader};
+/// let mut archive = StreamReader::<AnyHeader>::new(std::io::stdin().lock());
+/// while let Some(mut entry) = archive.next_entry().unwrap() {
+///     println!("{}", entry.header.name_lossy());
+///     if entry.header.name() == Some("init") {
+///         let mut data = vec![];
+///         entry.read_to_end(&mut data).unwrap();
+///     }
+/// }
+/// ```
+pub struct StreamReader<'b, C: CpioHeader> {
+    reader: ForwardReader<Box<dyn Read + 'b>>,
+    /// Bytes of data not yet read from the current entry
+    remaining: u64,
+    /// Bytes of padding after the data of the current entry
+    data_pad: usize,
+    /// Trailer entry has been read
+    finished: bool,
+    phantom: PhantomData<C>,
+}
+
+impl<'b, C: CpioHeader> StreamReader<'b, C> {
+    pub fn new(reader: impl Read + 'b) -> Self {
+        Self {
+            reader: ForwardReader::new(Box::new(reader)),
+            remaining: 0,
+            data_pad: 0,
+            finished: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Read next entry header, skipping unread data of the current entry
+    ///
+    /// Returns `None` once the `TRAILER!!!` entry has been read.
+    pub fn next_entry(&mut self) -> Result<Option<StreamEntry<'_, 'b, C>>, CpioError> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        // skip to next header
+        let skip = self.remaining + self.data_pad as u64;
+        let offset = self.reader.position() + skip;
+        self.reader.seek(SeekFrom::Current(skip as i64)).map_err(|e| {
+            if e.kind() == no_std_io2::io::ErrorKind::UnexpectedEof {
+                CpioError::Truncated { offset }
+            } else {
+                CpioError::StdIo(e)
+            }
+        })?;
+        self.remaining = 0;
+        self.data_pad = 0;
+
+        let mut deku_reader = Reader::new(&mut self.reader);
+        let header = C::from_reader_with_ctx(&mut deku_reader, ())
+            .map_err(|e| CpioError::from_deku(e, offset))?;
+        if header.name_bytes() == TRAILER.as_bytes() {
+            self.finished = true;
+            return Ok(None);
+        }
+
+        self.remaining = header.filesize();
+        self.data_pad = header.data_pad();
+        Ok(Some(StreamEntry { header, stream: self }))
+    }
+
+    pub fn into_inner(self) -> Box<dyn Read + 'b> {
+        self.reader.into_inner()
+    }
+}
+
+/// Current entry of [`StreamReader`], reading the data of the entry
+pub struct StreamEntry<'s, 'b, C: CpioHeader> {
+    pub header: C,
+    stream: &'s mut StreamReader<'b, C>,
+}
+
+impl<C: CpioHeader> StreamEntry<'_, '_, C> {
+    /// Bytes of data not yet read
+    pub fn remaining(&self) -> u64 {
+        self.stream.remaining
+    }
+}
+
+impl<C: CpioHeader> Read for StreamEntry<'_, '_, C> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, no_std_io2::io::Error> {
+        let len = buf.len().min(usize::try_from(self.stream.remaining).unwrap_or(usize::MAX));
+        if len == 0 {
+            return Ok(0);
+        }
+        let n = self.stream.reader.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(no_std_io2::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.stream.remaining -= n as u64;
+        Ok(n)
+    }
+}
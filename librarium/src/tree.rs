@@ -0,0 +1,197 @@
+extern crate std;
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::vec::Vec;
+
+use crate::cpio_header::pack_dev;
+use crate::reproducible::Inodes;
+use crate::{ArchiveWriter, CpioError, CpioHeader, Header};
+
+/// Build archive from a directory tree on disk
+///
+/// The header of each entry is created from the metadata of the file, with the name relative to
+/// the root directory. Entries are pushed in sorted order of name, with each directory before its
+/// contents, so the same tree always creates the same archive. The root directory isn't included.
+///
+/// | Type | Data |
+/// |------|------|
+/// | regular file | contents, read when written |
+/// | directory | none |
+/// | symlink | target path |
+/// | FIFO, block and character device, socket | none, with `rdev` |
+///
+/// Inode numbers are assigned from 1 in the order pushed, with hard links to the same file, the
+/// same `dev` and `ino` on disk, sharing the number. The numbers of each call to
+/// [`Self::push_to`] start from 1 again, so trees pushed to the same writer must be on different
+/// devices to not be written as links to each other.
+///
+/// # Example
+/// Create `initramfs.cpio` from `rootfs`.
+/// ```rust, no_run
+/// # use librarium::{ArchiveWriter, NewcHeader, TreeBuilder};
+/// # use std::fs::File;
+/// let file = File::create("initramfs.cpio").unwrap();
+/// let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(file));
+/// TreeBuilder::new("rootfs").push_to(&mut writer).unwrap();
+/// writer.write().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TreeBuilder {
+    root: PathBuf,
+}
+
+impl TreeBuilder {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Walk the directory tree and push each entry to `writer`
+    pub fn push_to<C: CpioHeader + core::fmt::Debug>(
+        &self,
+        writer: &mut ArchiveWriter<'_, C>,
+    ) -> Result<(), CpioError> {
+        self.push_dir(writer, &mut Inodes::default(), &self.root, b"")
+    }
+
+    /// Push the sorted entries of `dir`, with names prefixed by `prefix`
+    fn push_dir<C: CpioHeader + core::fmt::Debug>(
+        &self,
+        writer: &mut ArchiveWriter<'_, C>,
+        inodes: &mut Inodes<(u64, u64)>,
+        dir: &Path,
+        prefix: &[u8],
+    ) -> Result<(), CpioError> {
+        let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by(|a, b| a.file_name().as_bytes().cmp(b.file_name().as_bytes()));
+
+        for entry in entries {
+            let path = entry.path();
+            let mut name = prefix.to_vec();
+            if !name.is_empty() {
+                name.push(b'/');
+            }
+            name.extend_from_slice(entry.file_name().as_bytes());
+            let metadata = fs::symlink_metadata(&path)?;
+            let ino = inodes.assign(Some((metadata.dev(), metadata.ino())));
+            let header = header(&metadata, ino, name.clone());
+            log::trace!("pushing {path:?}");
+
+            let file_type = metadata.file_type();
+            if file_type.is_file() {
+                let reader = LazyFile::new(path, metadata.len());
+                writer.push_file(reader, header)?;
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(&path)?;
+                writer.push_file(Cursor::new(target.as_os_str().as_bytes().to_vec()), header)?;
+            } else if file_type.is_dir() {
+                writer.push_empty(header)?;
+                self.push_dir(writer, inodes, &path, &name)?;
+            } else if file_type.is_fifo()
+                || file_type.is_block_device()
+                || file_type.is_char_device()
+                || file_type.is_socket()
+            {
+                writer.push_empty(header)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Header of file with `metadata` and inode number `ino`
+fn header(metadata: &Metadata, ino: u32, name: Vec<u8>) -> Header {
+    let dev = metadata.dev();
+    let rdev = metadata.rdev();
+    Header {
+        ino,
+        mode: metadata.mode(),
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        nlink: u32::try_from(metadata.nlink()).unwrap_or(u32::MAX),
+        mtime: metadata.mtime().max(0) as u64,
+        dev: Some(old_dev(dev)),
+        devmajor: Some(major(dev)),
+        devminor: Some(minor(dev)),
+        rdev: Some(old_dev(rdev)),
+        rdevmajor: Some(major(rdev)),
+        rdevminor: Some(minor(rdev)),
+        name,
+    }
+}
+
+fn major(dev: u64) -> u32 {
+    libc::major(dev as libc::dev_t) as u32
+}
+
+fn minor(dev: u64) -> u32 {
+    libc::minor(dev as libc::dev_t) as u32
+}
+
+/// Device number with 8 bit minor number, as stored in single `dev` fields
+fn old_dev(dev: u64) -> u32 {
+    pack_dev(major(dev), minor(dev))
+}
+
+/// File opened when first read, to not keep all files of the tree open until written
+pub(crate) struct LazyFile {
+    path: PathBuf,
+    len: u64,
+    file: Option<File>,
+    pos: u64,
+}
+
+impl LazyFile {
+    /// File at `path` of `len` bytes
+    pub(crate) fn new(path: PathBuf, len: u64) -> Self {
+        Self { path, len, file: None, pos: 0 }
+    }
+}
+
+impl Read for LazyFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let mut file = File::open(&self.path)?;
+                file.seek(SeekFrom::Start(self.pos))?;
+                self.file.insert(file)
+            }
+        };
+        let n = file.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for LazyFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if let Some(file) = &mut self.file {
+            self.pos = file.seek(pos)?;
+            return Ok(self.pos);
+        }
+        let pos = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.len.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        self.pos = pos.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dev() {
+        let dev = libc::makedev(259, 0x12345) as u64;
+        assert_eq!((major(dev), minor(dev)), (259, 0x12345));
+        assert_eq!(old_dev(libc::makedev(8, 1) as u64), 0x801);
+    }
+}
@@ -4,10 +4,16 @@ use std::io::Cursor;
 use std::io::Read;
 use std::time::Duration;
 
+use deku::ctx::Endian;
 use librarium::CpioHeader;
+use librarium::CpioReader;
 use librarium::NewcHeader;
 use librarium::OdcHeader;
-use librarium::{ArchiveReader, ArchiveWriter};
+use librarium::{
+    AnyHeader, ArchiveReader, ArchiveWriter, BinHeader, CpioError, CrcHeader, Entries, FileType,
+    Format, Header, MultiArchiveReader, Permissions, Reproducible, StreamReader,
+};
+use librarium::{ArchiveEditor, Compression, Extractor, FileList, ListKind, TreeBuilder};
 use test_assets_ureq::{TestAsset, dl_test_files_backoff};
 
 // cpio -o -H newc > cpio-in.cpio
@@ -148,3 +154,1681 @@ fn test_simple_in_out_odc_files() {
 
     assert_eq!(first, second);
 }
+
+#[test_log::test]
+fn test_crc_checksum() {
+    let a_data = "a\n".as_bytes();
+    let b_data = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\n".as_bytes();
+
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<CrcHeader>::new(Box::new(&mut bytes));
+    let header = Header { name: "dir".into(), mode: 0o40755, nlink: 2, ..Header::default() };
+    writer.push_empty(header).unwrap();
+    let header = Header { name: "dir/a".into(), mode: 0o100644, nlink: 1, ..Header::default() };
+    writer.push_file(Cursor::new(a_data), header).unwrap();
+    let header = Header { name: "dir/b".into(), mode: 0o100644, nlink: 1, ..Header::default() };
+    writer.push_file(Cursor::new(b_data), header).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let mut bytes = bytes.into_inner();
+    assert_eq!(&bytes[..6], b"070702");
+
+    let mut archive = ArchiveReader::<CrcHeader>::from_reader(Cursor::new(&bytes)).unwrap();
+    assert_eq!(archive.objects.inner[0].header.check(), Some(0));
+    assert_eq!(archive.objects.inner[1].header.check(), Some(0x61 + 0x0a));
+    assert_eq!(archive.objects.inner[2].header.check(), Some(0x62 * 42 + 0x0a));
+
+    let mut b = Cursor::new(Vec::new());
+    archive.extract_by_name("dir/b", &mut b).unwrap().unwrap();
+    assert_eq!(b.into_inner(), b_data);
+    drop(archive);
+
+    // corrupt data of "dir/b"
+    let pos = bytes.windows(b_data.len()).position(|w| w == b_data).unwrap();
+    bytes[pos] = b'c';
+    let mut archive = ArchiveReader::<CrcHeader>::from_reader(Cursor::new(&bytes)).unwrap();
+    let mut b = Cursor::new(Vec::new());
+    let err = archive.extract_by_name("dir/b", &mut b).unwrap_err();
+    assert!(matches!(
+        err,
+        CpioError::ChecksumMismatch { expected, found }
+            if expected == 0x62 * 42 + 0x0a && found == 0x62 * 41 + 0x63 + 0x0a
+    ));
+}
+
+#[test_log::test]
+fn test_bin_endian() {
+    let a_data = "a\n".as_bytes();
+    let b_data = "bbb".as_bytes();
+
+    for endian in [Endian::Little, Endian::Big] {
+        let mut bytes = Cursor::new(vec![]);
+        let mut writer = ArchiveWriter::<BinHeader>::new(Box::new(&mut bytes));
+        writer.set_endian(endian);
+        writer.set_pad_len(512);
+        let header = Header { name: "dir".into(), mode: 0o40755, nlink: 2, ..Header::default() };
+        writer.push_empty(header).unwrap();
+        let header = Header {
+            name: "dir/a".into(),
+            mode: 0o100644,
+            nlink: 1,
+            mtime: 1_700_000_000,
+            ..Header::default()
+        };
+        writer.push_file(Cursor::new(a_data), header).unwrap();
+        let header = Header { name: "dir/b".into(), mode: 0o100644, ..Header::default() };
+        writer.push_file(Cursor::new(b_data), header).unwrap();
+        writer.write().unwrap();
+        drop(writer);
+        let bytes = bytes.into_inner();
+        assert_eq!(bytes.len(), 512);
+
+        let mut archive = ArchiveReader::<BinHeader>::from_reader(Cursor::new(&bytes)).unwrap();
+        assert_eq!(archive.objects.inner.len(), 4);
+        for object in &archive.objects.inner {
+            assert_eq!(object.header.endian(), endian);
+        }
+        assert_eq!(archive.objects.inner[1].header.mtime(), 1_700_000_000);
+
+        let mut a = Cursor::new(Vec::new());
+        archive.extract_by_name("dir/a", &mut a).unwrap().unwrap();
+        assert_eq!(a.into_inner(), a_data);
+        let mut b = Cursor::new(Vec::new());
+        archive.extract_by_name("dir/b", &mut b).unwrap().unwrap();
+        assert_eq!(b.into_inner(), b_data);
+    }
+}
+
+fn write_archive<C: CpioHeader + std::fmt::Debug>() -> Vec<u8> {
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<C>::new(Box::new(&mut bytes));
+    let header = Header { name: "dir".into(), mode: 0o40755, nlink: 2, ..Header::default() };
+    writer.push_empty(header).unwrap();
+    let header = Header { name: "dir/a".into(), mode: 0o100644, nlink: 1, ..Header::default() };
+    writer.push_file(Cursor::new("a\n".as_bytes()), header).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    bytes.into_inner()
+}
+
+#[test_log::test]
+fn test_detect_format() {
+    let archives = [
+        (Format::Newc, write_archive::<NewcHeader>()),
+        (Format::Crc, write_archive::<CrcHeader>()),
+        (Format::Odc, write_archive::<OdcHeader>()),
+        (Format::Bin, write_archive::<BinHeader>()),
+    ];
+    for (format, bytes) in archives {
+        // with leading bytes before archive
+        let mut with_offset = vec![0xff; 3];
+        with_offset.extend_from_slice(&bytes);
+        let mut archive = ArchiveReader::<AnyHeader>::detect(Cursor::new(with_offset), 3).unwrap();
+        assert_eq!(archive.format(), Some(format));
+        assert_eq!(archive.objects.inner.len(), 3);
+
+        let mut a = Cursor::new(Vec::new());
+        let header = archive.extract_by_name("dir/a", &mut a).unwrap().unwrap();
+        assert_eq!(header.mode, 0o100644);
+        assert_eq!(a.into_inner(), "a\n".as_bytes());
+    }
+
+    let err = ArchiveReader::<AnyHeader>::detect(Cursor::new(b"not a cpio archive"), 0);
+    assert!(matches!(err, Err(CpioError::UnknownFormat)));
+    let err = ArchiveReader::<AnyHeader>::detect(Cursor::new(b"07"), 0);
+    assert!(matches!(err, Err(CpioError::UnknownFormat)));
+}
+
+/// offset of the `n`th header with `magic`
+fn header_offset(bytes: &[u8], magic: &[u8], n: usize) -> usize {
+    bytes.windows(magic.len()).enumerate().filter(|(_, w)| *w == magic).nth(n).unwrap().0
+}
+
+#[test_log::test]
+fn test_malformed() {
+    let newc = write_archive::<NewcHeader>();
+    let offset = header_offset(&newc, b"070701", 1);
+
+    // ino
+    let mut bytes = newc.clone();
+    bytes[offset + 6] = b'g';
+    let err = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes)).err().unwrap();
+    assert!(matches!(err, CpioError::InvalidHex { offset: o } if o == offset as u64));
+
+    // name NUL terminator of "dir/a"
+    let mut bytes = newc.clone();
+    bytes[offset + 110 + 5] = b'b';
+    let err = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes)).err().unwrap();
+    assert!(matches!(err, CpioError::MissingNul { offset: o } if o == offset as u64));
+
+    // name that isn't UTF-8 is kept as bytes
+    let mut bytes = newc.clone();
+    bytes[offset + 110] = 0xff;
+    let archive = ArchiveReader::<AnyHeader>::from_reader(Cursor::new(bytes)).unwrap();
+    assert_eq!(archive.objects.inner[1].header.name_bytes(), b"\xffir/a");
+
+    // magic
+    let mut bytes = newc.clone();
+    bytes[offset + 5] = b'9';
+    let err = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes)).err().unwrap();
+    assert!(matches!(err, CpioError::InvalidMagic { offset: o } if o == offset as u64));
+
+    // data of "dir/a"
+    let bytes = newc[..offset + 112].to_vec();
+    let err = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes)).err().unwrap();
+    assert!(matches!(err, CpioError::Truncated { offset: o } if o == offset as u64));
+
+    // header of "dir/a"
+    let bytes = newc[..offset + 50].to_vec();
+    let err = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes)).err().unwrap();
+    assert!(matches!(err, CpioError::Truncated { offset: o } if o == offset as u64));
+
+    // namesize larger than archive
+    let mut bytes = newc.clone();
+    bytes[offset + 94..offset + 102].copy_from_slice(b"FFFFFFFF");
+    let err = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes)).err().unwrap();
+    assert!(matches!(err, CpioError::Truncated { offset: o } if o == offset as u64));
+
+    // mode
+    let odc = write_archive::<OdcHeader>();
+    let offset = header_offset(&odc, b"070707", 1);
+    let mut bytes = odc.clone();
+    bytes[offset + 18] = b'8';
+    let err = ArchiveReader::<OdcHeader>::from_reader(Cursor::new(bytes)).err().unwrap();
+    assert!(matches!(err, CpioError::InvalidOctal { offset: o } if o == offset as u64));
+}
+
+#[test_log::test]
+fn test_newc_name_padding() {
+    // header and name are padded to 4 bytes, including names that fill the padding exactly
+    for name in ["a", "ab", "abc", "abcd", "abcde"] {
+        let mut bytes = Cursor::new(vec![]);
+        let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+        let header = Header { name: name.into(), mode: 0o100644, ..Header::default() };
+        writer.push_file(Cursor::new("data".as_bytes()), header).unwrap();
+        writer.write().unwrap();
+        drop(writer);
+
+        let mut archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+        assert_eq!(archive.objects.inner[0].header.name(), Some(name));
+        let mut data = Cursor::new(Vec::new());
+        archive.extract_by_name(name, &mut data).unwrap().unwrap();
+        assert_eq!(data.into_inner(), b"data");
+    }
+}
+
+#[test_log::test]
+fn test_entry_reader() {
+    // larger than one copy chunk
+    let big: Vec<u8> = (0..0x5000_u32).map(|i| i as u8).collect();
+
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<CrcHeader>::new(Box::new(&mut bytes));
+    let header = Header { name: "big".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(big.clone()), header).unwrap();
+    let header = Header { name: "small".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(b"small".to_vec()), header).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut archive = ArchiveReader::<CrcHeader>::from_reader(bytes).unwrap();
+
+    // writer doesn't need to be `Seek`
+    let mut out: Vec<u8> = vec![];
+    archive.reader.extract_data(&archive.objects.inner[0], &mut out).unwrap();
+    assert_eq!(out, big);
+
+    // only the data of the entry is read
+    let mut reader = archive.reader.entry_reader(&archive.objects.inner[1]).unwrap();
+    assert_eq!(reader.remaining(), 5);
+    let mut first = [0; 2];
+    reader.read_exact(&mut first).unwrap();
+    assert_eq!(&first, b"sm");
+    let mut rest = vec![];
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"all");
+    assert_eq!(reader.remaining(), 0);
+}
+
+#[test_log::test]
+fn test_streaming_writer() {
+    let big: Vec<u8> = (0..0x5000_u32).map(|i| i as u8).collect();
+
+    let push = |writer: &mut ArchiveWriter<CrcHeader>| {
+        let header = Header { name: "dir".into(), mode: 0o40755, ..Header::default() };
+        writer.push_empty(header).unwrap();
+        let header = Header { name: "dir/big".into(), mode: 0o100644, ..Header::default() };
+        writer.push_file(Cursor::new(big.clone()), header).unwrap();
+    };
+
+    let mut buffered = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<CrcHeader>::new(Box::new(&mut buffered));
+    push(&mut writer);
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut streaming = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<CrcHeader>::new_streaming(Box::new(&mut streaming));
+    push(&mut writer);
+    writer.finish().unwrap();
+
+    assert_eq!(buffered.get_ref(), streaming.get_ref());
+    assert_eq!(streaming.get_ref().len() % 0x400, 0);
+
+    let mut archive = ArchiveReader::<CrcHeader>::from_reader(streaming).unwrap();
+    let mut out = vec![];
+    archive.extract_by_name("dir/big", &mut out).unwrap().unwrap();
+    assert_eq!(out, big);
+}
+
+#[test_log::test]
+fn test_streaming_writer_writes_on_push() {
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new_streaming(Box::new(&mut bytes));
+    let header = Header { name: "a".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(b"a\n"), header).unwrap();
+    drop(writer);
+
+    // header, name and data, without trailer
+    assert_eq!(bytes.get_ref().len(), 112 + 4);
+}
+
+/// `Read` only, returning at most `chunk` bytes per read
+struct Pipe<'a> {
+    bytes: &'a [u8],
+    chunk: usize,
+}
+
+impl Read for Pipe<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.chunk);
+        self.bytes.read(&mut buf[..len])
+    }
+}
+
+#[test_log::test]
+fn test_stream_reader() {
+    let big: Vec<u8> = (0..0x5000_u32).map(|i| i as u8).collect();
+
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<BinHeader>::new(Box::new(&mut bytes));
+    writer.set_endian(Endian::Big);
+    let header = Header { name: "big".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(big.clone()), header).unwrap();
+    let header = Header { name: "odd".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(b"odd".to_vec()), header).unwrap();
+    let header = Header { name: "small".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(b"small".to_vec()), header).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let bytes = bytes.into_inner();
+
+    for chunk in [1, 7, 0x1000, usize::MAX] {
+        let mut archive = StreamReader::<AnyHeader>::new(Pipe { bytes: &bytes, chunk });
+
+        // partially read, rest is skipped
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header.format(), Format::Bin);
+        assert_eq!(entry.header.name(), Some("big"));
+        let mut first = [0; 3];
+        entry.read_exact(&mut first).unwrap();
+        assert_eq!(first, big[..3]);
+        assert_eq!(entry.remaining(), 0x5000 - 3);
+
+        // not read
+        let entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header.name(), Some("odd"));
+
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header.name(), Some("small"));
+        let mut data = vec![];
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"small");
+
+        assert!(archive.next_entry().unwrap().is_none());
+        assert!(archive.next_entry().unwrap().is_none());
+    }
+
+    // truncated in data of entry
+    let bytes = write_archive::<NewcHeader>();
+    let trailer = header_offset(&bytes, b"070701", 2);
+    let mut archive =
+        StreamReader::<NewcHeader>::new(Pipe { bytes: &bytes[..trailer - 2], chunk: 3 });
+    assert_eq!(archive.next_entry().unwrap().unwrap().header.name(), Some("dir"));
+    assert_eq!(archive.next_entry().unwrap().unwrap().header.name(), Some("dir/a"));
+    assert!(matches!(archive.next_entry(), Err(CpioError::Truncated { .. })));
+}
+
+#[test_log::test]
+fn test_entries() {
+    let bytes = write_archive::<NewcHeader>();
+
+    // stop early, using reader between entries
+    let mut entries = Entries::<NewcHeader>::from_reader(Cursor::new(bytes.clone())).unwrap();
+    assert_eq!(entries.next().unwrap().unwrap().header.name(), Some("dir"));
+    let object = entries.next().unwrap().unwrap();
+    assert_eq!(object.header.name(), Some("dir/a"));
+    let mut out = vec![];
+    entries.reader.extract_data(&object, &mut out).unwrap();
+    assert_eq!(out, b"a\n");
+    assert_eq!(entries.next().unwrap().unwrap().header.name(), Some("TRAILER!!!"));
+    assert!(entries.next().is_none());
+
+    // same objects as ArchiveReader
+    let names: Vec<String> = Entries::<AnyHeader>::from_reader(Cursor::new(bytes.clone()))
+        .unwrap()
+        .map(|object| object.unwrap().header.name().unwrap().to_string())
+        .collect();
+    let archive = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes.clone())).unwrap();
+    let expected: Vec<String> = archive
+        .objects
+        .inner
+        .iter()
+        .map(|object| object.header.name().unwrap().to_string())
+        .collect();
+    assert_eq!(names, expected);
+
+    // entries before the error are returned, and iteration stops after the error
+    let trailer = header_offset(&bytes, b"070701", 2);
+    let mut corrupt = bytes.clone();
+    corrupt[trailer] = b'x';
+    let mut entries = Entries::<NewcHeader>::from_reader(Cursor::new(corrupt)).unwrap();
+    assert!(entries.next().unwrap().is_ok());
+    assert!(entries.next().unwrap().is_ok());
+    assert!(matches!(
+        entries.next(),
+        Some(Err(CpioError::InvalidMagic { offset })) if offset == trailer as u64
+    ));
+    assert!(entries.next().is_none());
+}
+
+#[test_log::test]
+fn test_path_index() {
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    for (name, data) in
+        [(".", None), ("./bin", None), ("./bin/sh", Some("old")), ("./binary", Some("b"))]
+            .into_iter()
+            .chain([("./bin/ls", Some("ls")), ("bin/sh", Some("new"))])
+    {
+        let header = Header { name: name.into(), ..Header::default() };
+        match data {
+            Some(data) => writer.push_file(Cursor::new(data.as_bytes().to_vec()), header),
+            None => writer.push_empty(header),
+        }
+        .unwrap();
+    }
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    for indexed in [false, true] {
+        if indexed {
+            archive.build_index();
+        }
+        assert!(archive.contains("/bin/sh"));
+        assert!(archive.contains("bin/"));
+        assert!(!archive.contains("bin/cat"));
+        assert!(!archive.contains("TRAILER!!!"));
+        assert_eq!(archive.metadata("bin/ls").unwrap().name, b"./bin/ls");
+
+        // last object with the same path
+        assert_eq!(archive.get("./bin/sh").unwrap().header.name(), Some("bin/sh"));
+        let mut out = vec![];
+        archive.extract_by_name("/bin/sh", &mut out).unwrap().unwrap();
+        assert_eq!(out, b"new");
+
+        let names: Vec<_> = archive
+            .entries_under("./bin")
+            .iter()
+            .map(|object| object.header.name().unwrap())
+            .collect();
+        assert_eq!(names, ["./bin/ls", "bin/sh"]);
+        assert_eq!(archive.entries_under("/").len(), 4);
+    }
+}
+
+#[test_log::test]
+fn test_multi_archive() {
+    // microcode segment, padded to 512 bytes
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<OdcHeader>::new(Box::new(&mut bytes));
+    writer.set_pad_len(0x200);
+    let header = Header { name: "kernel".into(), mode: 0o40755, ..Header::default() };
+    writer.push_empty(header).unwrap();
+    let header = Header { name: "kernel/a".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(b"old".to_vec()), header).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let mut bytes = bytes.into_inner();
+    assert_eq!(bytes.len() % 0x200, 0);
+
+    let second = bytes.len() as u64;
+    bytes.extend(write_archive::<NewcHeader>());
+    bytes.extend([0; 8]);
+    let third = bytes.len() as u64;
+    let mut last = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut last));
+    let header = Header { name: "./kernel/a".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(b"new".to_vec()), header).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    bytes.extend(last.into_inner());
+
+    let mut archive = MultiArchiveReader::from_reader(Cursor::new(bytes.clone())).unwrap();
+    let segments: Vec<_> =
+        archive.segments.iter().map(|segment| (segment.offset, segment.format)).collect();
+    assert_eq!(segments, [(0, Format::Odc), (second, Format::Newc), (third, Format::Newc)]);
+
+    let names: Vec<_> =
+        archive.merged().iter().map(|object| object.header.name().unwrap()).collect();
+    assert_eq!(names, ["kernel", "dir", "dir/a", "./kernel/a"]);
+
+    assert_eq!(archive.get("/kernel/a").unwrap().header.name(), Some("./kernel/a"));
+    let mut out = vec![];
+    archive.extract_by_name("kernel/a", &mut out).unwrap().unwrap();
+    assert_eq!(out, b"new");
+
+    // data after archive that isn't padding or an archive
+    bytes.extend(b"garbage");
+    let garbage = bytes.len() as u64 - 7;
+    assert!(matches!(
+        MultiArchiveReader::from_reader(Cursor::new(bytes)),
+        Err(CpioError::InvalidMagic { offset }) if offset == garbage
+    ));
+}
+
+/// Compress `bytes`, or `None` if the feature of `compression` isn't enabled
+#[allow(unused_variables)]
+fn compress(compression: Compression, bytes: &[u8]) -> Option<Vec<u8>> {
+    #[allow(unused_imports)]
+    use std::io::Write;
+
+    match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::best());
+            encoder.write_all(bytes).unwrap();
+            Some(encoder.finish().unwrap())
+        }
+        #[cfg(feature = "xz")]
+        Compression::Xz => {
+            let mut encoder = liblzma::write::XzEncoder::new(vec![], 6);
+            encoder.write_all(bytes).unwrap();
+            Some(encoder.finish().unwrap())
+        }
+        #[cfg(feature = "lzma")]
+        Compression::Lzma => {
+            let options = liblzma::stream::LzmaOptions::new_preset(6).unwrap();
+            let stream = liblzma::stream::Stream::new_lzma_encoder(&options).unwrap();
+            let mut encoder = liblzma::write::XzEncoder::new_stream(vec![], stream);
+            encoder.write_all(bytes).unwrap();
+            Some(encoder.finish().unwrap())
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Some(zstd::encode_all(bytes, 3).unwrap()),
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => {
+            // legacy frame of two blocks
+            let mut out = 0x184c_2102_u32.to_le_bytes().to_vec();
+            let (first, second) = bytes.split_at(bytes.len() / 2);
+            for block in [first, second] {
+                let block = lz4_flex::block::compress(block);
+                out.extend((block.len() as u32).to_le_bytes());
+                out.extend(block);
+            }
+            Some(out)
+        }
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => {
+            let mut encoder = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::best());
+            encoder.write_all(bytes).unwrap();
+            Some(encoder.finish().unwrap())
+        }
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+#[test_log::test]
+fn test_decompress() {
+    let archive = write_archive::<NewcHeader>();
+    for compression in [
+        Compression::Gzip,
+        Compression::Xz,
+        Compression::Lzma,
+        Compression::Zstd,
+        Compression::Lz4,
+        Compression::Bzip2,
+    ] {
+        let Some(compressed) = compress(compression, &archive) else {
+            assert!(matches!(
+                compression.decoder(&[][..]),
+                Err(CpioError::UnsupportedCompression(c)) if c == compression
+            ));
+            continue;
+        };
+        assert_eq!(Compression::from_magic(&compressed), Some(compression));
+        assert_eq!(compression.decompress(compressed.as_slice()).unwrap(), archive);
+
+        let mut archive = ArchiveReader::<AnyHeader>::detect(Cursor::new(compressed), 0).unwrap();
+        assert_eq!(archive.format(), Some(Format::Newc));
+        let mut out = vec![];
+        archive.extract_by_name("dir/a", &mut out).unwrap().unwrap();
+        assert_eq!(out, b"a\n");
+    }
+}
+
+#[cfg(feature = "gzip")]
+#[test_log::test]
+fn test_decompress_multi_archive() {
+    // uncompressed early segment, followed by compressed main segment
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    writer.set_pad_len(0x200);
+    let header = Header { name: "early".into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(b"early".to_vec()), header).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let mut bytes = bytes.into_inner();
+    let second = bytes.len() as u64;
+    bytes.extend(compress(Compression::Gzip, &write_archive::<OdcHeader>()).unwrap());
+
+    let mut archive = MultiArchiveReader::from_reader(Cursor::new(bytes)).unwrap();
+    let segments: Vec<_> = archive
+        .segments
+        .iter()
+        .map(|segment| (segment.offset, segment.format, segment.compression))
+        .collect();
+    assert_eq!(segments, [(0, Format::Newc, None), (second, Format::Odc, Some(Compression::Gzip))]);
+
+    let mut out = vec![];
+    archive.extract_by_name("early", &mut out).unwrap().unwrap();
+    assert_eq!(out, b"early");
+    let mut out = vec![];
+    archive.extract_by_name("dir/a", &mut out).unwrap().unwrap();
+    assert_eq!(out, b"a\n");
+}
+
+#[cfg(feature = "gzip")]
+#[test_log::test]
+fn test_decompress_seek() {
+    use std::cell::Cell;
+    use std::io::{Seek, SeekFrom};
+    use std::rc::Rc;
+
+    /// Counts the bytes read from `inner`
+    struct Counting<R> {
+        inner: R,
+        read: Rc<Cell<u64>>,
+    }
+    impl<R: Read> Read for Counting<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read.set(self.read.get() + n as u64);
+            Ok(n)
+        }
+    }
+    impl<R: Seek> Seek for Counting<R> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    // larger than the data kept when decompressing, so seeking back decompresses again
+    let big: Vec<u8> = (0..0x10000_u32).map(|i| (i % 251) as u8).collect();
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let file = |name: &str| Header { name: name.into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(big.clone()), file("big")).unwrap();
+    writer.push_file(Cursor::new(b"last".to_vec()), file("last")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let compressed = compress(Compression::Gzip, bytes.get_ref()).unwrap();
+
+    // objects are read in one pass of the compressed data
+    let read = Rc::new(Cell::new(0));
+    let counting = Counting { inner: Cursor::new(compressed.clone()), read: read.clone() };
+    ArchiveReader::<AnyHeader>::detect(counting, 0).unwrap();
+    assert!(read.get() < 2 * compressed.len() as u64);
+    let read = Rc::new(Cell::new(0));
+    let counting = Counting { inner: Cursor::new(compressed.clone()), read: read.clone() };
+    MultiArchiveReader::from_reader(counting).unwrap();
+    assert!(read.get() < 2 * compressed.len() as u64);
+
+    let truncated = compress(Compression::Gzip, &bytes.get_ref()[..0x8000]).unwrap();
+    assert!(ArchiveReader::<AnyHeader>::detect(Cursor::new(truncated.clone()), 0).is_err());
+    assert!(MultiArchiveReader::from_reader(Cursor::new(truncated)).is_err());
+
+    let mut archive = ArchiveReader::<AnyHeader>::detect(Cursor::new(compressed), 0).unwrap();
+    for _ in 0..2 {
+        let mut out = vec![];
+        archive.extract_by_name("last", &mut out).unwrap().unwrap();
+        assert_eq!(out, b"last");
+        let mut out = vec![];
+        archive.extract_by_name("big", &mut out).unwrap().unwrap();
+        assert_eq!(out, big);
+    }
+    let len = archive.reader.seek(SeekFrom::End(0)).unwrap();
+    assert_eq!(len, bytes.get_ref().len() as u64);
+}
+
+#[test_log::test]
+fn test_compressed_writer() {
+    let big: Vec<u8> = (0..0x5000_u32).map(|i| (i % 7) as u8).collect();
+    for compression in [
+        Compression::Gzip,
+        Compression::Xz,
+        Compression::Lzma,
+        Compression::Zstd,
+        Compression::Lz4,
+        Compression::Bzip2,
+    ] {
+        let mut bytes = vec![];
+        let writer =
+            ArchiveWriter::<NewcHeader>::new_compressed(Box::new(&mut bytes), compression, Some(1));
+        if matches!(writer, Err(CpioError::UnsupportedCompression(c)) if c == compression) {
+            continue;
+        }
+        let mut writer = writer.unwrap();
+        let header = Header { name: "big".into(), mode: 0o100644, ..Header::default() };
+        writer.push_file(Cursor::new(big.clone()), header).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(Compression::from_magic(&bytes), Some(compression));
+        if compression == Compression::Xz {
+            // CRC32 check in stream flags
+            assert_eq!(bytes[6..8], [0x00, 0x01]);
+        }
+        let decompressed = compression.decompress(bytes.as_slice()).unwrap();
+        assert_eq!(decompressed.len() % 0x400, 0);
+
+        let mut archive = ArchiveReader::<AnyHeader>::detect(Cursor::new(bytes), 0).unwrap();
+        let mut out = vec![];
+        archive.extract_by_name("big", &mut out).unwrap().unwrap();
+        assert_eq!(out, big);
+
+        // levels out of range of the compression, lz4 has no levels
+        let invalid: &[u32] = match compression {
+            Compression::Gzip | Compression::Xz | Compression::Lzma => &[10, 19],
+            Compression::Zstd => &[23, u32::MAX],
+            Compression::Bzip2 => &[0, 10, 19],
+            Compression::Lz4 => &[],
+        };
+        for &level in invalid {
+            let result = ArchiveWriter::<NewcHeader>::new_compressed(
+                Box::new(std::io::sink()),
+                compression,
+                Some(level),
+            );
+            assert!(
+                matches!(result, Err(CpioError::StdIo(ref e)) if e.kind() == std::io::ErrorKind::InvalidInput),
+                "{compression:?} level {level}"
+            );
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn test_tree_builder() {
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("usr/bin")).unwrap();
+    std::fs::write(root.join("usr/bin/b"), "b\n").unwrap();
+    std::fs::write(root.join("init"), "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(root.join("init"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::write(root.join("usr/a"), "a\n").unwrap();
+    std::fs::hard_link(root.join("usr/a"), root.join("usr/c")).unwrap();
+    symlink("usr/bin", root.join("bin")).unwrap();
+    let _socket = std::os::unix::net::UnixListener::bind(root.join("sock")).unwrap();
+
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    TreeBuilder::new(root).push_to(&mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    let entries: Vec<_> = archive
+        .objects
+        .inner
+        .iter()
+        .map(|object| (object.header.name().unwrap().to_string(), object.header.mode() & 0o170000))
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            ("bin".to_string(), 0o120000),
+            ("init".to_string(), 0o100000),
+            ("sock".to_string(), 0o140000),
+            ("usr".to_string(), 0o040000),
+            ("usr/a".to_string(), 0o100000),
+            ("usr/bin".to_string(), 0o040000),
+            ("usr/bin/b".to_string(), 0o100000),
+            ("usr/c".to_string(), 0o100000),
+            ("TRAILER!!!".to_string(), 0),
+        ]
+    );
+
+    // data only on the last link
+    let a = archive.metadata("usr/a").unwrap();
+    assert_eq!(a.nlink, 2);
+    assert_eq!(archive.get("usr/a").unwrap().header.filesize(), 0);
+    assert_eq!(archive.get("usr/c").unwrap().header.filesize(), 2);
+    let mut out = vec![];
+    archive.extract_by_name("usr/a", &mut out).unwrap();
+    assert_eq!(out, b"a\n");
+
+    let header = archive.metadata("init").unwrap();
+    assert_eq!(header.mode, 0o100755);
+    assert_eq!(header.nlink, 1);
+    let mut out = vec![];
+    archive.extract_by_name("init", &mut out).unwrap();
+    assert_eq!(out, b"#!/bin/sh\n");
+    let mut out = vec![];
+    archive.extract_by_name("bin", &mut out).unwrap();
+    assert_eq!(out, b"usr/bin");
+    let mut out = vec![];
+    archive.extract_by_name("usr/bin/b", &mut out).unwrap();
+    assert_eq!(out, b"b\n");
+
+    // inode numbers assigned in order, shared by links
+    let inos: Vec<_> =
+        archive.objects.inner[..8].iter().map(|object| object.header.ino()).collect();
+    assert_eq!(inos, [1, 2, 3, 4, 5, 6, 7, 5]);
+
+    // fits in the 16 bit fields of bin headers
+    let mut writer = ArchiveWriter::<BinHeader>::new(Box::new(Cursor::new(vec![])));
+    TreeBuilder::new(root).push_to(&mut writer).unwrap();
+    writer.write().unwrap();
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn test_extractor() {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let dir = Header { name: "dir".into(), mode: 0o40500, mtime: 1000, ..Header::default() };
+    writer.push_empty(dir).unwrap();
+    let file = Header { name: "dir/file".into(), mode: 0o100640, mtime: 2000, ..Header::default() };
+    writer.push_file(Cursor::new(b"data".to_vec()), file).unwrap();
+    let empty = Header { name: "empty".into(), mode: 0o100600, ..Header::default() };
+    writer.push_empty(empty).unwrap();
+    let link = Header { name: "link".into(), mode: 0o120777, ..Header::default() };
+    writer.push_file(Cursor::new(b"dir/file".to_vec()), link).unwrap();
+    let fifo = Header { name: "fifo".into(), mode: 0o10644, ..Header::default() };
+    writer.push_empty(fifo).unwrap();
+    let null = Header {
+        name: "null".into(),
+        mode: 0o20666,
+        rdevmajor: Some(1),
+        rdevminor: Some(3),
+        ..Header::default()
+    };
+    writer.push_empty(null).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let out = tempfile::tempdir().unwrap();
+    let mut archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    Extractor::new(out.path())
+        .ownership(false)
+        .skip_unpermitted(true)
+        .extract(&mut archive.reader, &archive.objects.inner)
+        .unwrap();
+
+    let path = out.path();
+    assert_eq!(std::fs::read(path.join("dir/file")).unwrap(), b"data");
+    let metadata = std::fs::metadata(path.join("dir/file")).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o640);
+    assert_eq!(metadata.mtime(), 2000);
+
+    // applied after contents
+    let metadata = std::fs::metadata(path.join("dir")).unwrap();
+    assert!(metadata.is_dir());
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o500);
+    assert_eq!(metadata.mtime(), 1000);
+
+    assert_eq!(std::fs::read(path.join("empty")).unwrap(), b"");
+    assert_eq!(std::fs::read_link(path.join("link")).unwrap(), std::path::Path::new("dir/file"));
+    let metadata = std::fs::symlink_metadata(path.join("fifo")).unwrap();
+    assert!(metadata.file_type().is_fifo());
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o644);
+
+    // only if permitted
+    if let Ok(metadata) = std::fs::symlink_metadata(path.join("null")) {
+        assert!(metadata.file_type().is_char_device());
+        assert_eq!(metadata.rdev(), 0x103);
+    }
+
+    // extracting again replaces files
+    std::fs::set_permissions(path.join("dir"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    Extractor::new(out.path())
+        .ownership(false)
+        .special_files(false)
+        .extract(&mut archive.reader, &archive.objects.inner)
+        .unwrap();
+    assert_eq!(std::fs::read(path.join("dir/file")).unwrap(), b"data");
+
+    // allow tempdir cleanup
+    std::fs::set_permissions(path.join("dir"), std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn test_file_type() {
+    let mut header = Header::new("dir", FileType::Directory, Permissions::from_bits(0o755));
+    assert_eq!(header.mode, 0o40755);
+    header.set_permissions(Permissions::OWNER_READ | Permissions::OWNER_EXECUTE);
+    assert_eq!(header.mode, 0o40500);
+    header.set_file_type(FileType::Regular);
+    assert_eq!(header.mode, 0o100500);
+
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let dir = Header::new("dir", FileType::Directory, Permissions::from_bits(0o755));
+    let result = writer.push_file(Cursor::new(b"data".to_vec()), dir);
+    assert!(matches!(result, Err(CpioError::UnexpectedData(FileType::Directory))));
+    let link = Header::new("link", FileType::Symlink, Permissions::from_bits(0o777));
+    assert!(matches!(writer.push_empty(link), Err(CpioError::MissingData(FileType::Symlink))));
+
+    let dir = Header::new("dir", FileType::Directory, Permissions::from_bits(0o1777));
+    writer.push_file(Cursor::new(vec![]), dir).unwrap();
+    let fifo = Header::new("fifo", FileType::Fifo, Permissions::from_bits(0o644));
+    writer.push_empty(fifo).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    let header = &archive.objects.inner[0].header;
+    assert_eq!(header.file_type(), Some(FileType::Directory));
+    assert!(header.permissions().contains(Permissions::STICKY));
+    assert_eq!(archive.objects.inner[1].header.file_type(), Some(FileType::Fifo));
+    assert_eq!(archive.objects.inner[2].header.file_type(), None);
+}
+
+#[test]
+fn test_push_special() {
+    fn push<C: CpioHeader + std::fmt::Debug>(writer: &mut ArchiveWriter<C>) {
+        let header =
+            |name: &str| Header { name: name.into(), mode: 0o644, nlink: 1, ..Header::default() };
+        writer.push_dir(header("dev")).unwrap();
+        writer.push_char_device(header("dev/console"), 5, 1).unwrap();
+        writer.push_block_device(header("dev/sda1"), 8, 1).unwrap();
+        writer.push_fifo(header("fifo")).unwrap();
+        writer.push_symlink(header("sh"), "busybox").unwrap();
+        writer.write().unwrap();
+    }
+
+    let mut newc = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut newc));
+    push(&mut writer);
+    drop(writer);
+    let mut odc = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<OdcHeader>::new(Box::new(&mut odc));
+    push(&mut writer);
+    drop(writer);
+    let mut bin = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<BinHeader>::new(Box::new(&mut bin));
+    push(&mut writer);
+    drop(writer);
+
+    for bytes in [newc, odc, bin] {
+        let mut archive = ArchiveReader::<AnyHeader>::detect(bytes, 0).unwrap();
+        let types: Vec<_> = archive.objects.inner.iter().map(|o| o.header.file_type()).collect();
+        assert_eq!(
+            types,
+            [
+                Some(FileType::Directory),
+                Some(FileType::CharDevice),
+                Some(FileType::BlockDevice),
+                Some(FileType::Fifo),
+                Some(FileType::Symlink),
+                None
+            ]
+        );
+        let console = archive.get("dev/console").unwrap();
+        assert_eq!(console.header.permissions(), Permissions::from_bits(0o644));
+        match archive.format().unwrap() {
+            Format::Newc => {
+                assert_eq!(console.header.rdevmajor(), Some(5));
+                assert_eq!(console.header.rdevminor(), Some(1));
+            }
+            _ => assert_eq!(console.header.rdev(), Some(0x501)),
+        }
+        assert_eq!(console.header.device(), Some((5, 1)));
+        assert_eq!(archive.get("dev/sda1").unwrap().header.device(), Some((8, 1)));
+        assert_eq!(archive.get("fifo").unwrap().header.device(), None);
+
+        assert_eq!(archive.symlink_target("sh").unwrap().as_deref(), Some(&b"busybox"[..]));
+        assert_eq!(archive.symlink_target("dev").unwrap(), None);
+        let sh = &archive.objects.inner[4];
+        assert_eq!(
+            sh.symlink_target(&mut archive.reader).unwrap().as_deref(),
+            Some(&b"busybox"[..])
+        );
+    }
+
+    // device numbers that don't fit
+    let mut writer = ArchiveWriter::<OdcHeader>::new(Box::new(Cursor::new(vec![])));
+    writer.push_char_device(Header::default(), 1023, 255).unwrap();
+    let result = writer.push_char_device(Header::default(), 1024, 0);
+    assert!(matches!(result, Err(CpioError::DeviceOutOfRange { major: 1024, minor: 0 })));
+    let result = writer.push_block_device(Header::default(), 8, 256);
+    assert!(matches!(result, Err(CpioError::DeviceOutOfRange { major: 8, minor: 256 })));
+    let mut writer = ArchiveWriter::<BinHeader>::new(Box::new(Cursor::new(vec![])));
+    let result = writer.push_char_device(Header::default(), 256, 0);
+    assert!(matches!(result, Err(CpioError::DeviceOutOfRange { .. })));
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(Cursor::new(vec![])));
+    writer.push_char_device(Header::default(), 4095, 0xfffff).unwrap();
+    let result = writer.push_symlink(Header::default(), "");
+    assert!(matches!(result, Err(CpioError::MissingData(FileType::Symlink))));
+}
+
+#[test]
+fn test_large_values() {
+    use deku::{DekuContainerRead, DekuContainerWrite};
+
+    // odc stores 11 octal digits of mtime and filesize
+    let header = Header { name: "a".into(), mtime: 1 << 32, ..Header::default() };
+    let odc = OdcHeader::from_header(header, 5 << 30).unwrap();
+    let bytes = odc.to_bytes().unwrap();
+    let (_, odc) = OdcHeader::from_bytes((&bytes, 0)).unwrap();
+    assert_eq!(odc.mtime(), 1 << 32);
+    assert_eq!(odc.filesize(), 5 << 30);
+
+    let header = || Header { name: "a".into(), ..Header::default() };
+    let result = OdcHeader::from_header(header(), 1 << 33);
+    assert!(matches!(
+        result,
+        Err(CpioError::FieldOverflow {
+            format: Format::Odc,
+            field: "filesize",
+            value: 0x2_0000_0000
+        })
+    ));
+    let result = OdcHeader::from_header(Header { ino: 0o1000000, ..header() }, 0);
+    assert!(matches!(result, Err(CpioError::FieldOverflow { field: "ino", .. })));
+    let result = NewcHeader::from_header(header(), 1 << 32);
+    assert!(matches!(
+        result,
+        Err(CpioError::FieldOverflow { format: Format::Newc, field: "filesize", .. })
+    ));
+    let result = BinHeader::from_header(Header { uid: 0x10000, ..header() }, 0);
+    assert!(matches!(
+        result,
+        Err(CpioError::FieldOverflow { format: Format::Bin, field: "uid", .. })
+    ));
+
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(Cursor::new(vec![])));
+    let result = writer.push_empty(Header { mtime: 1 << 32, ..header() });
+    assert!(matches!(
+        result,
+        Err(CpioError::FieldOverflow { field: "mtime", value: 0x1_0000_0000, .. })
+    ));
+    let mut writer = ArchiveWriter::<OdcHeader>::new(Box::new(Cursor::new(vec![])));
+    writer.push_empty(Header { mtime: 1 << 32, ..header() }).unwrap();
+
+    // nlink set when writing hard links
+    let mut odc = OdcHeader::from_header(header(), 0).unwrap();
+    assert!(matches!(
+        odc.set_nlink(0o1000000),
+        Err(CpioError::FieldOverflow { field: "nlink", .. })
+    ));
+    let mut bin = BinHeader::from_header(header(), 0).unwrap();
+    assert!(matches!(
+        bin.set_nlink(0x10000),
+        Err(CpioError::FieldOverflow { format: Format::Bin, field: "nlink", value: 0x10000 })
+    ));
+    bin.set_nlink(0xffff).unwrap();
+    assert_eq!(bin.nlink(), 0xffff);
+}
+
+#[test_log::test]
+fn test_hard_links() {
+    fn push_links<C: CpioHeader + std::fmt::Debug>(writer: &mut ArchiveWriter<C>) {
+        let link = |name: &str| Header {
+            ino: 5,
+            mode: 0o100644,
+            nlink: 2,
+            devmajor: Some(8),
+            dev: Some(0x801),
+            name: name.into(),
+            ..Header::default()
+        };
+        writer.push_file(Cursor::new(b"data".to_vec()), link("a")).unwrap();
+        let other = Header { ino: 6, mode: 0o100644, nlink: 1, ..link("b") };
+        writer.push_file(Cursor::new(b"other".to_vec()), other).unwrap();
+        writer.push_empty(link("c")).unwrap();
+        writer.push_empty(link("d")).unwrap();
+        writer.write().unwrap();
+    }
+
+    // newc: data only on the last link
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    push_links(&mut writer);
+    drop(writer);
+    let newc = bytes.into_inner();
+    let mut archive = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(newc.clone())).unwrap();
+    let sizes: Vec<_> = archive
+        .objects
+        .inner
+        .iter()
+        .map(|object| (object.header.nlink(), object.header.filesize()))
+        .collect();
+    assert_eq!(sizes, [(3, 0), (1, 5), (3, 0), (3, 4), (1, 0)]);
+    let groups = archive.hard_links();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].links, [0, 2, 3]);
+    assert_eq!(groups[0].data, Some(3));
+    let a = &archive.objects.inner[0];
+    assert_eq!(archive.data_object(a).header.name(), Some("d"));
+    for name in ["a", "c", "d"] {
+        let mut out = vec![];
+        archive.extract_by_name(name, &mut out).unwrap();
+        assert_eq!(out, b"data");
+    }
+
+    // odc: data on every link
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<OdcHeader>::new(Box::new(&mut bytes));
+    push_links(&mut writer);
+    drop(writer);
+    let mut archive = ArchiveReader::<OdcHeader>::from_reader(bytes).unwrap();
+    let sizes: Vec<_> = archive
+        .objects
+        .inner
+        .iter()
+        .map(|object| (object.header.nlink(), object.header.filesize()))
+        .collect();
+    assert_eq!(sizes, [(3, 4), (1, 5), (3, 4), (3, 4), (1, 0)]);
+    for object in &archive.objects.inner[..4] {
+        let mut out = vec![];
+        archive.reader.extract_data(object, &mut out).unwrap();
+        assert_eq!(out, if object.header.name() == Some("b") { &b"other"[..] } else { b"data" });
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let out = tempfile::tempdir().unwrap();
+        let mut archive = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(newc)).unwrap();
+        Extractor::new(out.path())
+            .ownership(false)
+            .extract(&mut archive.reader, &archive.objects.inner)
+            .unwrap();
+        let a = std::fs::metadata(out.path().join("a")).unwrap();
+        for name in ["c", "d"] {
+            let metadata = std::fs::metadata(out.path().join(name)).unwrap();
+            assert_eq!(metadata.ino(), a.ino());
+        }
+        assert_eq!(a.nlink(), 3);
+        assert_eq!(std::fs::read(out.path().join("a")).unwrap(), b"data");
+        assert_eq!(std::fs::read(out.path().join("b")).unwrap(), b"other");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extractor_safe() {
+    use librarium::{RejectReason, Rejected};
+
+    let outside = tempfile::tempdir().unwrap();
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let file = |name: &str| Header { name: name.into(), mode: 0o100644, ..Header::default() };
+    writer.push_file(Cursor::new(b"a".to_vec()), file("../../escape")).unwrap();
+    writer.push_file(Cursor::new(b"b".to_vec()), file("/abs")).unwrap();
+    let link = Header { name: "link".into(), mode: 0o120777, ..Header::default() };
+    let target = outside.path().as_os_str().as_encoded_bytes().to_vec();
+    writer.push_file(Cursor::new(target), link).unwrap();
+    writer.push_file(Cursor::new(b"c".to_vec()), file("link/file")).unwrap();
+    writer.push_file(Cursor::new(b"d".to_vec()), file("./dir/../file")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let out = tempfile::tempdir().unwrap();
+    let dest = out.path().join("dest");
+    let mut archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    let rejected = Extractor::new(&dest)
+        .ownership(false)
+        .extract(&mut archive.reader, &archive.objects.inner)
+        .unwrap();
+    assert_eq!(
+        rejected,
+        [
+            Rejected { name: "../../escape".into(), reason: RejectReason::ParentDir },
+            Rejected { name: "link/file".into(), reason: RejectReason::Symlink },
+            Rejected { name: "./dir/../file".into(), reason: RejectReason::ParentDir },
+        ]
+    );
+    assert_eq!(std::fs::read(dest.join("abs")).unwrap(), b"b");
+    assert!(std::fs::symlink_metadata(dest.join("link")).unwrap().file_type().is_symlink());
+    assert!(!outside.path().join("file").exists());
+    assert!(!out.path().join("escape").exists());
+
+    // follows symlinks if not safe
+    let rejected = Extractor::new(&dest)
+        .ownership(false)
+        .safe(false)
+        .extract(&mut archive.reader, &archive.objects.inner)
+        .unwrap();
+    assert!(rejected.is_empty());
+    assert_eq!(std::fs::read(outside.path().join("file")).unwrap(), b"c");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extractor_replaced_link() {
+    // the first link to a file is replaced by a symlink out of the destination
+    let outside = tempfile::tempdir().unwrap();
+    std::fs::write(outside.path().join("file"), b"original").unwrap();
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let link = |name: &str| Header {
+        name: name.into(),
+        ino: 5,
+        mode: 0o100644,
+        nlink: 2,
+        ..Header::default()
+    };
+    writer.push_empty(link("a")).unwrap();
+    let symlink = Header { name: "a".into(), ino: 6, mode: 0o120777, ..Header::default() };
+    let target = outside.path().join("file").into_os_string().into_encoded_bytes();
+    writer.push_file(Cursor::new(target), symlink).unwrap();
+    writer.push_file(Cursor::new(b"PWNED".to_vec()), link("b")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let out = tempfile::tempdir().unwrap();
+    let mut archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    let rejected = Extractor::new(out.path())
+        .ownership(false)
+        .extract(&mut archive.reader, &archive.objects.inner)
+        .unwrap();
+    assert!(rejected.is_empty());
+    assert_eq!(std::fs::read(outside.path().join("file")).unwrap(), b"original");
+    assert!(std::fs::symlink_metadata(out.path().join("a")).unwrap().file_type().is_symlink());
+    let b = std::fs::symlink_metadata(out.path().join("b")).unwrap();
+    assert!(b.is_file());
+    assert_eq!(std::fs::read(out.path().join("b")).unwrap(), b"PWNED");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extractor_dirs_depth_first() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // children before their parents, with a parent that can't be searched
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let dir = |name: &str, mode: u32, mtime: u64| Header {
+        name: name.into(),
+        mode: 0o040000 | mode,
+        mtime,
+        nlink: 2,
+        ..Header::default()
+    };
+    writer.push_dir(dir("a/b/c", 0o755, 100)).unwrap();
+    writer.push_dir(dir("a/b", 0o755, 200)).unwrap();
+    writer.push_dir(dir("a", 0o600, 300)).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let out = tempfile::tempdir().unwrap();
+    let mut archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    Extractor::new(out.path())
+        .ownership(false)
+        .extract(&mut archive.reader, &archive.objects.inner)
+        .unwrap();
+    let a = out.path().join("a");
+    let metadata = std::fs::metadata(&a).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o600);
+    assert_eq!(metadata.modified().unwrap(), std::time::UNIX_EPOCH + Duration::from_secs(300));
+
+    std::fs::set_permissions(&a, std::fs::Permissions::from_mode(0o755)).unwrap();
+    for (name, mtime) in [("a/b", 200), ("a/b/c", 100)] {
+        let metadata = std::fs::metadata(out.path().join(name)).unwrap();
+        assert_eq!(
+            metadata.modified().unwrap(),
+            std::time::UNIX_EPOCH + Duration::from_secs(mtime)
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_names() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = b"caf\xe9".to_vec();
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let dir = Header::new(name.clone(), FileType::Directory, Permissions::from_bits(0o755));
+    writer.push_dir(dir).unwrap();
+    let mut file = name.clone();
+    file.extend_from_slice(b"/na\xefve");
+    let header = Header::new(file.clone(), FileType::Regular, Permissions::from_bits(0o644));
+    writer.push_file(Cursor::new(b"a".to_vec()), header).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut archive =
+        ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes.get_ref())).unwrap();
+    let header = &archive.objects.inner[1].header;
+    assert_eq!(header.name_bytes(), file);
+    assert_eq!(header.name(), None);
+    assert_eq!(header.name_lossy(), "caf\u{fffd}/na\u{fffd}ve");
+    assert_eq!(header.as_header().name, file);
+    assert!(archive.contains(b"./caf\xe9/na\xefve"));
+
+    // extracted with the same bytes
+    let out = tempfile::tempdir().unwrap();
+    Extractor::new(out.path())
+        .ownership(false)
+        .extract(&mut archive.reader, &archive.objects.inner)
+        .unwrap();
+    let path = out.path().join(std::ffi::OsStr::from_bytes(&file));
+    assert_eq!(std::fs::read(&path).unwrap(), b"a");
+
+    // written again byte for byte
+    let mut rewritten = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut rewritten));
+    for object in &archive.objects.inner[..2] {
+        let mut data = vec![];
+        archive.reader.extract_data(object, &mut data).unwrap();
+        writer.push_file(Cursor::new(data), object.header.as_header()).unwrap();
+    }
+    writer.write().unwrap();
+    drop(writer);
+    assert_eq!(rewritten.get_ref(), bytes.get_ref());
+
+    // built from the tree
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    TreeBuilder::new(out.path()).push_to(&mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    assert!(archive.contains(&file));
+
+    // symlink targets are bytes too
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let link = Header::new("link", FileType::Symlink, Permissions::from_bits(0o777));
+    writer.push_symlink(link, b"caf\xe9").unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let mut archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    assert_eq!(archive.symlink_target("link").unwrap().unwrap(), b"caf\xe9");
+
+    // NUL would end the name early when read
+    let header = || Header::new(b"a\0b".to_vec(), FileType::Regular, Permissions::from_bits(0o644));
+    assert!(matches!(NewcHeader::from_header(header(), 0), Err(CpioError::InvalidName)));
+    assert!(matches!(CrcHeader::from_header(header(), 0), Err(CpioError::InvalidName)));
+    assert!(matches!(OdcHeader::from_header(header(), 0), Err(CpioError::InvalidName)));
+    assert!(matches!(BinHeader::from_header(header(), 0), Err(CpioError::InvalidName)));
+}
+
+#[test]
+fn test_reproducible() {
+    fn write(order: &[usize], offset: u32) -> Vec<u8> {
+        let file = |name: &str, ino| Header {
+            ino: ino + offset,
+            mode: 0o100644,
+            uid: 1000 + offset,
+            gid: 1000,
+            nlink: 1,
+            mtime: 1_800_000_000 + u64::from(offset),
+            dev: Some(offset),
+            devmajor: Some(offset),
+            devminor: Some(1),
+            name: name.into(),
+            ..Header::default()
+        };
+        let mut headers = [
+            Header { mode: 0o40755, nlink: 2, mtime: 1000, ..file("./usr", 1) },
+            file("usr/b", 2),
+            Header { nlink: 2, ..file("/usr/a", 3) },
+            Header { nlink: 2, ..file("usr/c", 3) },
+            file("init", 4),
+        ]
+        .map(Some);
+
+        let mut bytes = Cursor::new(vec![]);
+        let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+        let reproducible = Reproducible::new().clamp_mtime(1_700_000_000).root_ownership(true);
+        writer.set_reproducible(reproducible).unwrap();
+        for &i in order {
+            let header = headers[i].take().unwrap();
+            let data = if header.file_type() == Some(FileType::Directory) {
+                vec![]
+            } else {
+                header.name.clone()
+            };
+            writer.push_file(Cursor::new(data), header).unwrap();
+        }
+        writer.write().unwrap();
+        drop(writer);
+        bytes.into_inner()
+    }
+
+    let bytes = write(&[0, 1, 2, 3, 4], 0);
+    assert_eq!(bytes, write(&[4, 3, 1, 0, 2], 7));
+
+    let archive = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes)).unwrap();
+    let objects: Vec<_> = archive
+        .objects
+        .inner
+        .iter()
+        .map(|object| {
+            let header = &object.header;
+            (header.name().unwrap(), header.ino(), header.uid(), header.mtime(), header.devmajor())
+        })
+        .collect();
+    assert_eq!(
+        objects,
+        [
+            ("init", 1, 0, 1_700_000_000, Some(0)),
+            ("./usr", 2, 0, 1000, Some(0)),
+            ("/usr/a", 3, 0, 1_700_000_000, Some(0)),
+            ("usr/b", 4, 0, 1_700_000_000, Some(0)),
+            ("usr/c", 3, 0, 1_700_000_000, Some(0)),
+            ("TRAILER!!!", 0, 0, 0, Some(0)),
+        ]
+    );
+    assert_eq!(archive.hard_links()[0].links, [2, 4]);
+
+    // objects pushed before would keep their inode numbers
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(Cursor::new(vec![])));
+    let header = Header { name: "a".into(), ino: 1, mode: 0o100644, ..Header::default() };
+    writer.push_empty(header).unwrap();
+    let err = writer.set_reproducible(Reproducible::new()).unwrap_err();
+    assert!(matches!(err, CpioError::StdIo(e) if e.kind() == std::io::ErrorKind::InvalidInput));
+}
+
+#[test]
+fn test_file_list() {
+    let src = tempfile::tempdir().unwrap();
+    std::fs::write(src.path().join("init"), b"#!/bin/sh\n").unwrap();
+    let root = src.path().to_str().unwrap();
+    let list = format!(
+        "# comment\n\
+         \n\
+         dir /dev 0755 0 0\n\
+         nod /dev/console 0600 0 0 c 5 1\n\
+         nod /dev/sda 0660 0 6 b 8 0\n\
+         dir /bin 0755 0 0\n\
+         slink /bin/sh busybox 0777 0 0\n\
+         file /init {root}/init 0755 0 0 /linuxrc\n\
+         pipe /fifo 0644 1000 1000\n\
+         sock /sock 0644 0 0\n"
+    );
+    let parsed = FileList::parse(&list).unwrap();
+    assert_eq!(parsed.entries.len(), 8);
+    // written without the comment and empty line
+    let entries = list.split_once("\n\n").unwrap().1;
+    assert_eq!(parsed.to_string(), entries);
+
+    let now = std::time::SystemTime::now();
+    let now = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    parsed.push_to(&mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut archive =
+        ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes.get_ref())).unwrap();
+    // current time for entries other than regular files, as gen_init_cpio without -t
+    let init_mtime = std::fs::metadata(src.path().join("init")).unwrap().modified().unwrap();
+    let init_mtime = init_mtime.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    assert_eq!(archive.get("init").unwrap().header.mtime(), init_mtime);
+    assert!(archive.get("dev").unwrap().header.mtime() >= now);
+    let console = archive.get("dev/console").unwrap();
+    assert_eq!(console.header.file_type(), Some(FileType::CharDevice));
+    assert_eq!(console.header.device(), Some((5, 1)));
+    assert_eq!(archive.get("dev").unwrap().header.ino(), 721);
+    assert_eq!(archive.get("fifo").unwrap().header.uid(), 1000);
+    assert_eq!(archive.symlink_target("bin/sh").unwrap().unwrap(), b"busybox");
+    assert_eq!(archive.hard_links()[0].links.len(), 2);
+    let mut data = vec![];
+    archive.extract_by_name("linuxrc", &mut data).unwrap();
+    assert_eq!(data, b"#!/bin/sh\n");
+
+    // dumped list creates the same archive after extracting
+    let out = tempfile::tempdir().unwrap();
+    Extractor::new(out.path())
+        .ownership(false)
+        .special_files(false)
+        .extract(&mut archive.reader, &archive.objects.inner)
+        .unwrap();
+    let out_root = out.path().to_str().unwrap();
+    let dumped = FileList::from_archive(&mut archive, out_root).unwrap();
+    assert_eq!(dumped.to_string(), entries.replace(root, out_root));
+    assert_eq!(FileList::parse(&dumped.to_string()).unwrap(), dumped);
+
+    let mut rewritten = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut rewritten));
+    dumped.push_to(&mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let rewritten = ArchiveReader::<NewcHeader>::from_reader(rewritten).unwrap();
+    let headers = |archive: &ArchiveReader<NewcHeader>| -> Vec<_> {
+        archive
+            .objects
+            .inner
+            .iter()
+            .map(|o| (o.header.name().unwrap().to_string(), o.header.mode(), o.header.filesize()))
+            .collect()
+    };
+    assert_eq!(headers(&rewritten), headers(&archive));
+
+    // mtime of all entries, as gen_init_cpio -t
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    FileList { mtime: Some(1234), ..parsed }.push_to(&mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let archive = ArchiveReader::<NewcHeader>::from_reader(bytes).unwrap();
+    assert!(archive.objects.inner[..9].iter().all(|object| object.header.mtime() == 1234));
+
+    // invalid lines
+    let err = FileList::parse("dir /dev 0755 0 0\nfile /init\n").unwrap_err();
+    assert!(matches!(err, CpioError::InvalidList { line: 2, reason: "missing field" }));
+    let err = FileList::parse("dir /dev 0789 0 0\n").unwrap_err();
+    assert!(matches!(err, CpioError::InvalidList { line: 1, reason: "invalid mode" }));
+    let err = FileList::parse("link /a /b 0755 0 0\n").unwrap_err();
+    assert!(matches!(err, CpioError::InvalidList { line: 1, reason: "unknown type" }));
+
+    // nod of another file type
+    let mut list = FileList::parse("nod /dev/null 0666 0 0 c 1 3\n").unwrap();
+    let ListKind::Nod { file_type, .. } = &mut list.entries[0].kind else { unreachable!() };
+    *file_type = FileType::Regular;
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(Cursor::new(vec![])));
+    let err = list.push_to(&mut writer).unwrap_err();
+    assert!(matches!(err, CpioError::StdIo(e) if e.kind() == std::io::ErrorKind::InvalidInput));
+}
+
+#[test]
+fn test_archive_editor() {
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    let file = |name: &str| Header::new(name, FileType::Regular, Permissions::from_bits(0o644));
+    writer
+        .push_dir(Header::new("bin", FileType::Directory, Permissions::from_bits(0o755)))
+        .unwrap();
+    writer.push_file(Cursor::new(b"old".to_vec()), file("bin/busybox")).unwrap();
+    writer
+        .push_symlink(
+            Header::new("bin/sh", FileType::Symlink, Permissions::from_bits(0o777)),
+            "busybox",
+        )
+        .unwrap();
+    writer.push_file(Cursor::new(b"motd".to_vec()), file("etc/motd")).unwrap();
+    writer
+        .push_file(Cursor::new(b"linked".to_vec()), Header { ino: 9, nlink: 2, ..file("a") })
+        .unwrap();
+    writer.push_empty(Header { ino: 9, nlink: 2, ..file("b") }).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let archive =
+        ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes.into_inner())).unwrap();
+    let mut editor = ArchiveEditor::new(archive).unwrap();
+    assert!(editor.replace("/bin/busybox", Cursor::new(b"new busybox".to_vec())));
+    assert!(editor.remove("etc/motd"));
+    assert!(!editor.remove("etc/motd"));
+    assert!(editor.rename("bin/sh", "bin/ash"));
+    // data of the link is kept with the link containing it removed
+    assert!(editor.remove("b"));
+    editor.get_mut("a").unwrap().uid = 1000;
+    editor.insert(file("init"), Cursor::new(b"#!/bin/sh\n".to_vec()));
+    let names: Vec<_> = editor.headers().map(|header| header.name_lossy().into_owned()).collect();
+    assert_eq!(names, ["bin", "bin/busybox", "bin/ash", "a", "init"]);
+
+    // written in another format
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<OdcHeader>::new(Box::new(&mut bytes));
+    editor.push_to(&mut writer).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+
+    let mut archive = ArchiveReader::<OdcHeader>::from_reader(bytes).unwrap();
+    let data = |archive: &mut ArchiveReader<OdcHeader>, name: &str| {
+        let mut out = vec![];
+        archive.extract_by_name(name, &mut out).unwrap().unwrap();
+        out
+    };
+    assert_eq!(data(&mut archive, "bin/busybox"), b"new busybox");
+    assert_eq!(data(&mut archive, "a"), b"linked");
+    assert_eq!(data(&mut archive, "init"), b"#!/bin/sh\n");
+    assert_eq!(archive.symlink_target("bin/ash").unwrap().unwrap(), b"busybox");
+    let a = archive.get("a").unwrap();
+    assert_eq!((a.header.uid(), a.header.nlink()), (1000, 1));
+    assert!(!archive.contains("etc/motd"));
+    assert!(!archive.contains("bin/sh"));
+
+    // object not read from the archive
+    let header = Header { name: "new".into(), mode: 0o100644, ..Header::default() };
+    let header = OdcHeader::from_header(header, 1).unwrap();
+    let data = librarium::Data::Reader(Box::new(Cursor::new(b"a".to_vec())));
+    archive.objects.inner.insert(0, librarium::Object::new(header, data));
+    let err = ArchiveEditor::new(archive).err().unwrap();
+    assert!(matches!(err, CpioError::StdIo(e) if e.kind() == std::io::ErrorKind::InvalidInput));
+}
+
+#[test]
+fn test_append() {
+    let file = |name: &str| Header::new(name, FileType::Regular, Permissions::from_bits(0o644));
+    let mut bytes = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<NewcHeader>::new(Box::new(&mut bytes));
+    writer.push_file(Cursor::new(b"a".to_vec()), file("a")).unwrap();
+    writer.push_file(Cursor::new(b"b".to_vec()), file("b")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    assert_eq!(bytes.get_ref().len(), 0x400);
+
+    let mut writer = ArchiveWriter::<NewcHeader>::new_append(Box::new(&mut bytes)).unwrap();
+    writer.push_file(Cursor::new(vec![b'c'; 0x400]), file("c")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    assert_eq!(bytes.get_ref().len(), 0x800);
+
+    let mut archive =
+        ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes.get_ref().clone())).unwrap();
+    let names: Vec<_> =
+        archive.objects.inner.iter().map(|object| object.header.name().unwrap()).collect();
+    assert_eq!(names, ["a", "b", "c", "TRAILER!!!"]);
+    let mut out = vec![];
+    archive.extract_by_name("c", &mut out).unwrap();
+    assert_eq!(out, [b'c'; 0x400]);
+
+    // rest of a shorter archive overwritten
+    let mut writer = ArchiveWriter::<NewcHeader>::new_append(Box::new(&mut bytes)).unwrap();
+    writer.set_pad_len(0);
+    writer.push_file(Cursor::new(b"d".to_vec()), file("d")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    assert_eq!(bytes.get_ref().len(), 0x800);
+    let archive = ArchiveReader::<NewcHeader>::from_reader(Cursor::new(bytes.get_ref())).unwrap();
+    assert_eq!(archive.objects.inner.len(), 5);
+    drop(archive);
+    let trailer = bytes.get_ref().windows(10).rposition(|w| w == b"TRAILER!!!").unwrap();
+    assert!(bytes.get_ref()[trailer + 10..].iter().all(|b| *b == 0));
+
+    // empty archive
+    let mut empty = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<OdcHeader>::new_append(Box::new(&mut empty)).unwrap();
+    writer.push_file(Cursor::new(b"a".to_vec()), file("a")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    assert_eq!(ArchiveReader::<OdcHeader>::from_reader(empty).unwrap().objects.inner.len(), 2);
+
+    // without trailer
+    let mut truncated = Cursor::new(bytes.get_ref()[..trailer - 110].to_vec());
+    let err = ArchiveWriter::<NewcHeader>::new_append(Box::new(&mut truncated)).err().unwrap();
+    assert!(matches!(err, CpioError::Truncated { .. }));
+
+    // other format
+    let err = ArchiveWriter::<OdcHeader>::new_append(Box::new(&mut bytes)).err().unwrap();
+    assert!(matches!(err, CpioError::InvalidMagic { offset: 0 }));
+
+    // multi-segment archive, the later segment isn't overwritten
+    let mut multi = bytes.get_ref().clone();
+    multi.extend(write_archive::<NewcHeader>());
+    let mut multi = Cursor::new(multi);
+    let err = ArchiveWriter::<NewcHeader>::new_append(Box::new(&mut multi)).err().unwrap();
+    assert!(matches!(err, CpioError::TrailingData { offset: 0x800 }));
+    assert_eq!(multi.get_ref()[..0x800], bytes.get_ref()[..]);
+    assert_eq!(multi.get_ref()[0x800..], write_archive::<NewcHeader>());
+
+    // byte order of binary headers kept
+    let mut big = Cursor::new(vec![]);
+    let mut writer = ArchiveWriter::<BinHeader>::new(Box::new(&mut big));
+    writer.set_endian(Endian::Big);
+    writer.push_file(Cursor::new(b"a".to_vec()), file("a")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let mut writer = ArchiveWriter::<BinHeader>::new_append(Box::new(&mut big)).unwrap();
+    writer.push_file(Cursor::new(b"b".to_vec()), file("b")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let archive = ArchiveReader::<BinHeader>::from_reader(&mut big).unwrap();
+    let endians: Vec<_> =
+        archive.objects.inner.iter().map(|object| object.header.endian()).collect();
+    assert_eq!(endians, [Endian::Big; 3]);
+    drop(archive);
+
+    // format of the archive kept with AnyHeader
+    let mut odc = Cursor::new(write_archive::<OdcHeader>());
+    let mut writer = ArchiveWriter::<AnyHeader>::new_append(Box::new(&mut odc)).unwrap();
+    writer.push_file(Cursor::new(b"b".to_vec()), file("b")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let archive = ArchiveReader::<AnyHeader>::from_reader(odc).unwrap();
+    assert!(archive.objects.inner.iter().all(|object| object.header.format() == Format::Odc));
+    let names: Vec<_> =
+        archive.objects.inner.iter().map(|object| object.header.name().unwrap()).collect();
+    assert_eq!(names.last(), Some(&"TRAILER!!!"));
+    assert!(names.contains(&"b"));
+
+    let mut writer = ArchiveWriter::<AnyHeader>::new_append(Box::new(&mut big)).unwrap();
+    writer.push_file(Cursor::new(b"c".to_vec()), file("c")).unwrap();
+    writer.write().unwrap();
+    drop(writer);
+    let archive = ArchiveReader::<AnyHeader>::from_reader(big).unwrap();
+    let headers: Vec<_> = archive
+        .objects
+        .inner
+        .iter()
+        .map(|object| (object.header.format(), object.header.endian()))
+        .collect();
+    assert_eq!(headers, [(Format::Bin, Endian::Big); 4]);
+}